@@ -11,4 +11,6 @@ fn main() {
 
     println!("cargo:rustc-env=GIT_HASH={}", git_hash);
     println!("cargo:rustc-env=GIT_HASH_SHORT={}", &git_hash[0..8]);
+
+    glib_build_tools::compile_resources(&["data"], "data/gall.gresource.xml", "gall.gresource");
 }
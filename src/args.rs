@@ -25,6 +25,25 @@ pub enum Commands {
     Stop,
     /// Toggle the app launcher visibility
     Apps,
+    /// Pin an app to the top of the launcher
+    Pin {
+        /// Desktop file name (e.g. firefox.desktop) or config app name
+        id: String,
+    },
+    /// Unpin a previously pinned app
+    Unpin {
+        /// Desktop file name (e.g. firefox.desktop) or config app name
+        id: String,
+    },
+    /// List apps launched by the daemon
+    Ps,
+    /// Show past launches and their outcome
+    Log(LogArgs),
+    /// Switch the bundled theme until the daemon restarts
+    Theme {
+        /// light, dark, compact, spotlight or none
+        name: String,
+    },
 }
 
 #[derive(Args)]
@@ -41,3 +60,18 @@ pub struct DaemonArgs {
     #[arg(long = "keep-open", short = 'k')]
     pub keep_open: bool,
 }
+
+#[derive(Args)]
+pub struct LogArgs {
+    /// How many of the latest launches to show
+    #[arg(short = 'n', long, default_value_t = 20)]
+    pub lines: usize,
+
+    /// Keep printing launches as they finish
+    #[arg(short, long)]
+    pub follow: bool,
+
+    /// Open the log viewer in the running daemon instead
+    #[arg(short, long, conflicts_with = "follow")]
+    pub window: bool,
+}
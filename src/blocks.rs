@@ -1,41 +1,12 @@
 use crate::gtk;
+use crate::history::{self, LaunchRecord};
 use crate::misc;
 
+use std::rc::Rc;
+
 use gtk::prelude::*;
 use gtk::{gdk, glib};
 
-pub fn create_icon_widget(icon_str: &str, size: i32) -> Option<gtk::Image> {
-    let path = misc::expand_tilde(icon_str).expect("could not expand path");
-    if path.is_file() {
-        if let Ok(texture) = gdk::Texture::from_file(&gtk::gio::File::for_path(&path)) {
-            let image = gtk::Image::from_paintable(Some(&texture));
-            image.set_pixel_size(size);
-            return Some(image);
-        }
-    } // bad
-
-    let display = gdk::Display::default()?;
-    let icon_theme = gtk::IconTheme::for_display(&display);
-
-    let icon_paintable = icon_theme.lookup_icon(
-        icon_str,
-        &["application-x-executable"], // bad?
-        size,
-        1,
-        gtk::TextDirection::None,
-        gtk::IconLookupFlags::PRELOAD,
-    );
-
-    let image = gtk::Image::builder()
-        .overflow(gtk::Overflow::Hidden)
-        .name("app-row-image")
-        .pixel_size(size)
-        .paintable(&icon_paintable)
-        .build();
-
-    Some(image)
-}
-
 pub(crate) fn create_error_window(app: &gtk::Application, error: misc::CommandError) {
     let error_window = gtk::Window::builder()
         .title("Gall - Command Error")
@@ -183,3 +154,183 @@ pub(crate) fn create_error_window(app: &gtk::Application, error: misc::CommandEr
     });
     error_window.add_controller(key_controller);
 }
+
+/// Past launches, newest first, with their output and a way to run them again
+pub(crate) fn create_log_window<F: Fn(&LaunchRecord) + 'static>(app: &gtk::Application, on_rerun: F) {
+    let log_window = gtk::Window::builder()
+        .title("Gall - Launch Log")
+        .default_width(900)
+        .default_height(500)
+        .resizable(true)
+        .build();
+    log_window.set_application(Some(app));
+
+    let mut records = history::read_all();
+    records.reverse();
+    let records = Rc::new(records);
+
+    let paned = gtk::Paned::builder()
+        .name("log-box")
+        .orientation(gtk::Orientation::Horizontal)
+        .position(360)
+        .build();
+
+    let list = gtk::ListBox::builder()
+        .name("log-list")
+        .selection_mode(gtk::SelectionMode::Single)
+        .build();
+
+    for record in records.iter() {
+        let label = gtk::Label::builder()
+            .halign(gtk::Align::Start)
+            .margin_start(8)
+            .margin_end(8)
+            .margin_top(4)
+            .margin_bottom(4)
+            .build();
+        label.set_markup(&format!(
+            "<b>{}</b>  <small>{}</small>\n<small>{}</small>",
+            glib::markup_escape_text(&record.id),
+            glib::markup_escape_text(&record.time_str()),
+            glib::markup_escape_text(&record.outcome),
+        ));
+
+        let row = gtk::ListBoxRow::builder().child(&label).build();
+        if record.failed {
+            row.add_css_class("failed");
+        }
+        list.append(&row);
+    }
+
+    let list_scroll = gtk::ScrolledWindow::builder()
+        .name("log-list-scroll")
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .child(&list)
+        .build();
+    paned.set_start_child(Some(&list_scroll));
+
+    let details = gtk::Box::builder()
+        .name("log-details")
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(10)
+        .margin_start(15)
+        .margin_end(15)
+        .margin_top(15)
+        .margin_bottom(15)
+        .build();
+
+    let command_label = gtk::Label::builder()
+        .name("log-command")
+        .halign(gtk::Align::Start)
+        .wrap(true)
+        .selectable(true)
+        .build();
+    details.append(&command_label);
+
+    let output_view = |name: &str, title: &str| {
+        let label = gtk::Label::builder().halign(gtk::Align::Start).build();
+        label.set_markup(&format!("<b>{title}:</b>"));
+        details.append(&label);
+
+        let textview = gtk::TextView::builder().editable(false).monospace(true).build();
+        let scrolled = gtk::ScrolledWindow::builder()
+            .name(name)
+            .hscrollbar_policy(gtk::PolicyType::Automatic)
+            .vscrollbar_policy(gtk::PolicyType::Automatic)
+            .vexpand(true)
+            .child(&textview)
+            .build();
+        details.append(&scrolled);
+        textview.buffer()
+    };
+    let stdout_buffer = output_view("log-stdout-scroll", "STDOUT");
+    let stderr_buffer = output_view("log-stderr-scroll", "STDERR");
+
+    let button_box = gtk::Box::builder()
+        .name("log-btn-box")
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(10)
+        .halign(gtk::Align::End)
+        .build();
+
+    let rerun_btn = gtk::Button::builder().name("log-rerun-btn").label("Run again").build();
+    let copy_btn = gtk::Button::builder().name("log-copy-btn").label("Copy output").build();
+    let close_btn = gtk::Button::builder().name("log-close-btn").label("Close").build();
+    button_box.append(&rerun_btn);
+    button_box.append(&copy_btn);
+    button_box.append(&close_btn);
+    details.append(&button_box);
+
+    paned.set_end_child(Some(&details));
+    log_window.set_child(Some(&paned));
+
+    let selected = move |list: &gtk::ListBox| {
+        let index = list.selected_row()?.index();
+        usize::try_from(index).ok()
+    };
+
+    {
+        let records = records.clone();
+        list.connect_row_selected(move |_, row| {
+            let Some(record) = row.and_then(|r| records.get(r.index() as usize)) else {
+                return;
+            };
+
+            let pid = record.pid.map_or(String::new(), |p| format!(", pid {p}"));
+            command_label.set_markup(&format!(
+                "<b>{}</b>\n<tt>{}</tt>\n<small>{}, ran {:.1}s{pid}</small>",
+                glib::markup_escape_text(&record.id),
+                glib::markup_escape_text(&record.command),
+                glib::markup_escape_text(&record.outcome),
+                record.runtime_ms as f64 / 1000.0,
+            ));
+            stdout_buffer.set_text(&record.stdout);
+            stderr_buffer.set_text(&record.stderr);
+        });
+    }
+
+    {
+        let (records, list) = (records.clone(), list.clone());
+        rerun_btn.connect_clicked(move |_| {
+            if let Some(record) = selected(&list).and_then(|i| records.get(i)) {
+                on_rerun(record);
+            }
+        });
+    }
+
+    {
+        let (records, list) = (records.clone(), list.clone());
+        copy_btn.connect_clicked(move |_| {
+            let Some(record) = selected(&list).and_then(|i| records.get(i)) else {
+                return;
+            };
+
+            if let Some(display) = gdk::Display::default() {
+                display
+                    .clipboard()
+                    .set_text(&format!("$ {}\n{}{}", record.command, record.stdout, record.stderr));
+            }
+        });
+    }
+
+    {
+        let window = log_window.clone();
+        close_btn.connect_clicked(move |_| window.close());
+    }
+
+    let key_controller = gtk::EventControllerKey::new();
+    let window = log_window.clone();
+    key_controller.connect_key_pressed(move |_controller, keyval, _keycode, _state| {
+        if keyval == gdk::Key::Escape {
+            window.close();
+            return glib::Propagation::Stop;
+        }
+        glib::Propagation::Proceed
+    });
+    log_window.add_controller(key_controller);
+
+    if let Some(first) = list.row_at_index(0) {
+        list.select_row(Some(&first));
+    }
+    log_window.present();
+}
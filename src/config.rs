@@ -1,30 +1,479 @@
-use crate::misc;
+use crate::{misc, terminal};
 
 use serde::Deserialize;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize, Clone)]
 pub(crate) struct AppEntry {
+    /// Desktop file name for discovered apps, defaults to `name` for config ones
+    pub id: Option<String>,
     pub name: String,
     pub gend: Option<String>,
     pub desc: Option<String>,
     pub icon: Option<String>,
     pub exec: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Working directory, desktop files set it with `Path=`
+    pub cwd: Option<PathBuf>,
+    /// Variables to set, or unset with `false`, on top of `[environment]`
+    #[serde(default)]
+    pub env: HashMap<String, EnvValue>,
+    /// Start from an empty environment instead of the daemon's
+    #[serde(default)]
+    pub clear_env: bool,
+    /// Run in the `terminal`, desktop files set it with `Terminal=true`
+    #[serde(default)]
+    pub terminal: bool,
+    /// Startup notification, desktop files set it with `StartupNotify=`, see
+    /// `launcher::startup_id`
+    pub startup_notify: Option<bool>,
+    /// Overrides for `[launch]`, also settable under `[launch.apps."<id>"]`
+    #[serde(default)]
+    pub launch: LaunchOptions,
+    /// `launch` over the global options, filled in once the config is loaded
+    #[serde(skip)]
+    pub policy: LaunchPolicy,
+    /// Desktop file this entry came from
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+impl AppEntry {
+    pub fn id(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Commands to run for `args`: one, unless the desktop file only takes a single file or URL
+    /// (`%f`/`%u`) and there are several, then one per argument as the spec says
+    pub fn commands(&self, args: &[String]) -> Vec<String> {
+        let codes = match self.path {
+            Some(_) => field_codes(&self.exec),
+            None => Vec::new(),
+        };
+        let single = codes.iter().any(|c| matches!(c, 'f' | 'u')) && !codes.iter().any(|c| matches!(c, 'F' | 'U'));
+
+        if single && args.len() > 1 {
+            args.iter().map(|arg| self.command(std::slice::from_ref(arg))).collect()
+        } else {
+            vec![self.command(args)]
+        }
+    }
+
+    /// `exec` with `args` in the desktop file's `%f`/`%F`/`%u`/`%U`, quoted for `sh`. They're
+    /// appended for config apps, whose `exec` is a shell command, and Execs without those codes
+    fn command(&self, args: &[String]) -> String {
+        let quoted: Vec<String> = args.iter().map(|a| terminal::quote(a)).collect();
+        let (mut command, took_args) = match self.path {
+            Some(_) => self.expand_field_codes(&quoted),
+            None => (self.exec.clone(), false),
+        };
+
+        if !took_args && !quoted.is_empty() {
+            command.push(' ');
+            command.push_str(&quoted.join(" "));
+        }
+        command
+    }
+
+    // and whether there was a code for the arguments
+    fn expand_field_codes(&self, quoted: &[String]) -> (String, bool) {
+        let mut command = String::with_capacity(self.exec.len());
+        let mut took_args = false;
+        let mut chars = self.exec.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                command.push(c);
+                continue;
+            }
+
+            let expansion = match chars.next() {
+                Some('%') => "%".to_owned(),
+                // `commands` splits the arguments between instances
+                Some('f' | 'u') => {
+                    took_args = true;
+                    quoted.first().cloned().unwrap_or_default()
+                }
+                Some('F' | 'U') => {
+                    took_args = true;
+                    quoted.join(" ")
+                }
+                Some('i') => self.icon.as_ref().map_or(String::new(), |i| format!("--icon {}", terminal::quote(i))),
+                Some('c') => terminal::quote(&self.name),
+                Some('k') => self.path.as_ref().map_or(String::new(), |p| terminal::quote(&p.to_string_lossy())),
+                // deprecated codes
+                _ => String::new(),
+            };
+
+            // `app %u` loses the separator too, and `app --file=%f` the whole option
+            if expansion.is_empty() {
+                if command.ends_with('=') {
+                    command.truncate(command.rfind(' ').map_or(0, |i| i + 1));
+                }
+                if command.ends_with(' ') {
+                    command.pop();
+                }
+            }
+            command.push_str(&expansion);
+        }
+
+        (command, took_args)
+    }
+
+    pub fn has_alias(&self, query: &str) -> bool {
+        let query = query.trim();
+        !query.is_empty() && self.aliases.iter().any(|a| a.eq_ignore_ascii_case(query))
+    }
+}
+
+/// `NAME = "value"` sets a variable, `NAME = false` unsets it
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub(crate) enum EnvValue {
+    Set(String),
+    Unset(bool),
+}
+
+/// Per-app launch options, unset ones come from `[launch]`
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub(crate) struct LaunchOptions {
+    pub failure_timeout: Option<f64>,
+    pub success_codes: Option<Vec<i32>>,
+    pub show_error: Option<bool>,
+    pub capture_output: Option<bool>,
+}
+
+impl LaunchOptions {
+    // set fields win, the rest come from `other`
+    fn or(self, other: &LaunchOptions) -> Self {
+        Self {
+            failure_timeout: self.failure_timeout.or(other.failure_timeout),
+            success_codes: self.success_codes.or_else(|| other.success_codes.clone()),
+            show_error: self.show_error.or(other.show_error),
+            capture_output: self.capture_output.or(other.capture_output),
+        }
+    }
+}
+
+/// `[launch]`, how launches are judged
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct LaunchConfig {
+    /// Seconds, a failing exit later than this is the app closing, not failing to start
+    pub failure_timeout: f64,
+    /// Exit codes that aren't failures
+    pub success_codes: Vec<i32>,
+    /// Open the error window when a launch fails
+    pub show_error: bool,
+    /// Keep stdout/stderr in per-launch logs, otherwise they go to /dev/null
+    pub capture_output: bool,
+    /// Options by app id, for apps that can't set `launch` themselves
+    pub apps: HashMap<String, LaunchOptions>,
+}
+
+impl Default for LaunchConfig {
+    fn default() -> Self {
+        Self {
+            failure_timeout: 3.0,
+            success_codes: vec![0],
+            show_error: true,
+            capture_output: true,
+            apps: HashMap::new(),
+        }
+    }
+}
+
+impl LaunchConfig {
+    pub fn resolve(&self, options: &LaunchOptions) -> LaunchPolicy {
+        let timeout = options.failure_timeout.unwrap_or(self.failure_timeout);
+
+        LaunchPolicy {
+            failure_timeout: Duration::try_from_secs_f64(timeout).unwrap_or_else(|_| {
+                eprintln!("Invalid failure_timeout {timeout}, using 3 seconds");
+                Duration::from_secs(3)
+            }),
+            success_codes: options.success_codes.clone().unwrap_or_else(|| self.success_codes.clone()),
+            show_error: options.show_error.unwrap_or(self.show_error),
+            capture_output: options.capture_output.unwrap_or(self.capture_output),
+            cwd: None,
+            env: Vec::new(),
+            clear_env: false,
+            startup_id: None,
+        }
+    }
+}
+
+/// Launch options with nothing left unset, and the environment to launch in
+#[derive(Debug, Clone)]
+pub(crate) struct LaunchPolicy {
+    pub failure_timeout: Duration,
+    pub success_codes: Vec<i32>,
+    pub show_error: bool,
+    pub capture_output: bool,
+    /// `None` is the home directory, the daemon itself runs in `/`
+    pub cwd: Option<PathBuf>,
+    /// Applied in order, `None` unsets
+    pub env: Vec<(String, Option<String>)>,
+    pub clear_env: bool,
+    /// Activation token or startup notification id, per launch
+    pub startup_id: Option<String>,
+}
+
+impl Default for LaunchPolicy {
+    fn default() -> Self {
+        LaunchConfig::default().resolve(&LaunchOptions::default())
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Layout {
+    #[default]
+    List,
+    Grid,
+}
+
+/// Options every picker understands, under `[pickers.<name>]`
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct PickerOptions {
+    pub icon_size: i32,
+    pub symbolic_icons: bool,
+    pub layout: Layout,
+    /// Only used by the grid layout
+    pub columns: u32,
+    /// Show the preview pane when the picker opens
+    pub preview: bool,
+    /// Modifier for the +1..9 shortcuts, or "none"
+    pub quick_select: String,
+}
+
+impl Default for PickerOptions {
+    fn default() -> Self {
+        Self {
+            icon_size: 48,
+            symbolic_icons: false,
+            layout: Layout::List,
+            columns: 4,
+            preview: false,
+            quick_select: "Alt".to_owned(),
+        }
+    }
+}
+
+/// Window width or height, pixels or a percentage of the monitor (`600`, `"600px"`, `"30%"`)
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub(crate) enum Dimension {
+    Pixels(i32),
+    Text(String),
+}
+
+impl Dimension {
+    pub fn resolve(&self, total: i32) -> i32 {
+        let size = match self {
+            Dimension::Pixels(px) => Some(*px),
+            Dimension::Text(text) => parse_dimension(text.trim(), total),
+        };
+
+        size.unwrap_or_else(|| {
+            eprintln!("Invalid window size {self:?}, using 40%");
+            total * 2 / 5
+        })
+        .clamp(1, total.max(1))
+    }
+}
+
+fn parse_dimension(text: &str, total: i32) -> Option<i32> {
+    if let Some(percent) = text.strip_suffix('%') {
+        let percent = percent.trim().parse::<f32>().ok()?;
+        return Some((total as f32 * percent / 100.0).round() as i32);
+    }
+
+    text.strip_suffix("px").unwrap_or(text).trim().parse().ok()
+}
+
+/// Where the window sits on its monitor, margins push it away from the anchored edges
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Anchor {
+    #[default]
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Window size and placement, under `[window]`
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct WindowConfig {
+    pub width: Dimension,
+    pub height: Dimension,
+    pub anchor: Anchor,
+    pub margin_top: i32,
+    pub margin_bottom: i32,
+    pub margin_left: i32,
+    pub margin_right: i32,
+    /// Connector name (`DP-1`, `eDP-1`), the focused monitor is used if unset. Plain windows
+    /// only take their size from it, the window manager still picks where they open
+    pub monitor: Option<String>,
+    /// Cancel the picker when another window takes the focus
+    pub hide_on_focus_loss: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: Dimension::Text("30%".to_owned()),
+            height: Dimension::Text("40%".to_owned()),
+            anchor: Anchor::Center,
+            margin_top: 0,
+            margin_bottom: 0,
+            margin_left: 0,
+            margin_right: 0,
+            monitor: None,
+            hide_on_focus_loss: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct PickersConfig {
+    pub apps: PickerOptions,
 }
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct ConfigLoad {
-    pub css_reload: bool,
+    /// Bundled theme under the user CSS, see `styles::THEMES`
+    pub theme: Option<String>,
+    /// Reload the config and styles when they change on disk, read at startup
+    #[serde(default = "default_watch")]
+    pub watch: bool,
+    /// Preset name, or a template with `{argv}` or `{cmd}`, see `terminal::resolve`
     pub terminal: Option<String>,
+    /// `terminal` as a template, filled in once the config is loaded
+    #[serde(skip)]
+    pub terminal_command: Option<String>,
+    #[serde(default = "default_typo_tolerance")]
+    pub typo_tolerance: usize,
+    #[serde(default)]
+    pub pinned: Vec<String>,
+    /// Alias to app id, for apps that can't set `aliases` themselves
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    #[serde(default)]
+    pub launch: LaunchConfig,
+    /// Set (or unset with `false`) for every launch
+    #[serde(default)]
+    pub environment: HashMap<String, EnvValue>,
+    #[serde(default)]
+    pub window: WindowConfig,
+    #[serde(default)]
+    pub pickers: PickersConfig,
+    /// Preset bindings: "default", "emacs" or "vim"
+    #[serde(default = "default_keymap")]
+    pub keymap: String,
+    /// Key chord to action name, on top of the preset
+    #[serde(default)]
+    pub keys: HashMap<String, String>,
     pub apps: Vec<AppEntry>,
 }
 
+fn default_typo_tolerance() -> usize {
+    2
+}
+
+impl ConfigLoad {
+    /// Policy for app `id`, the global one if it's not a known app
+    pub fn launch_policy(&self, id: &str) -> LaunchPolicy {
+        match self.apps.iter().find(|a| a.id() == id) {
+            Some(app) => app.policy.clone(),
+            None => self.global_policy(),
+        }
+    }
+
+    /// `[launch]` and `[environment]` alone
+    pub fn global_policy(&self) -> LaunchPolicy {
+        let mut policy = self.launch.resolve(&LaunchOptions::default());
+        policy.env = env_changes(&self.environment);
+        policy
+    }
+}
+
+// sorted so launches don't depend on hash order
+fn env_changes(env: &HashMap<String, EnvValue>) -> Vec<(String, Option<String>)> {
+    let mut changes: Vec<(String, Option<String>)> = env
+        .iter()
+        .filter_map(|(name, value)| match value {
+            EnvValue::Set(value) => Some((name.clone(), Some(value.clone()))),
+            EnvValue::Unset(false) => Some((name.clone(), None)),
+            EnvValue::Unset(true) => {
+                eprintln!("Environment variable {name} = true, use a string to set it or false to unset");
+                None
+            }
+        })
+        .collect();
+
+    changes.sort();
+    changes
+}
+
+/// Ids of gall's own launches (like `gall:editor`), no app can have one
+pub(crate) const RESERVED_ID_PREFIX: &str = "gall:";
+
+fn default_watch() -> bool {
+    true
+}
+
+fn default_keymap() -> String {
+    "default".to_owned()
+}
+
+/// Load the config, or an empty one if it can't be read
 pub(crate) fn load_config(filepath: &PathBuf) -> Arc<ConfigLoad> {
-    let desktop_paths = crate::DESKTOP_PATHS.map(misc::expand_tilde).map(Option::unwrap);
+    let cfg = read_config(filepath).unwrap_or_else(|| ConfigLoad {
+        theme: None,
+        watch: default_watch(),
+        terminal: None,
+        terminal_command: None,
+        typo_tolerance: default_typo_tolerance(),
+        pinned: Vec::new(),
+        aliases: HashMap::new(),
+        launch: LaunchConfig::default(),
+        environment: HashMap::new(),
+        window: WindowConfig::default(),
+        pickers: PickersConfig::default(),
+        keymap: default_keymap(),
+        keys: HashMap::new(),
+        apps: Vec::new(),
+    });
 
-    let cfg = std::fs::read_to_string(&filepath)
+    with_apps(cfg)
+}
+
+/// Load the config again, `None` if it's broken so the caller can keep the one it has
+pub(crate) fn reload_config(filepath: &PathBuf) -> Option<Arc<ConfigLoad>> {
+    read_config(filepath).map(with_apps)
+}
+
+fn read_config(filepath: &PathBuf) -> Option<ConfigLoad> {
+    std::fs::read_to_string(&filepath)
         .map_err(|e| {
             eprintln!(
                 "Error reading config file {}: {}",
@@ -40,25 +489,60 @@ pub(crate) fn load_config(filepath: &PathBuf) -> Arc<ConfigLoad> {
                     e.message()
                 );
             })
-        });
-
-    let mut cfg = match cfg {
-        Ok(cfg) => cfg,
-        Err(_) => ConfigLoad {
-            css_reload: false,
-            terminal: None,
-            apps: Vec::new(),
-        },
-    };
+        })
+        .ok()
+}
+
+// desktop apps go after the config ones, then `[aliases]` and `[launch]` are attached to both
+fn with_apps(mut cfg: ConfigLoad) -> Arc<ConfigLoad> {
+    let desktop_paths = crate::DESKTOP_PATHS.map(misc::expand_tilde).map(Option::unwrap);
+
+    cfg.apps.extend(load_apps(&desktop_paths));
+    cfg.apps.retain(|app| {
+        let reserved = app.id().starts_with(RESERVED_ID_PREFIX);
+        if reserved {
+            eprintln!("Skipping app {}, ids starting with {RESERVED_ID_PREFIX} are gall's own", app.id());
+        }
+        !reserved
+    });
+
+    cfg.terminal_command = crate::terminal::resolve(cfg.terminal.as_deref());
+    if cfg.terminal_command.is_none() {
+        let count = cfg.apps.iter().filter(|a| a.terminal).count();
+        if count > 0 {
+            eprintln!("No terminal found for {count} terminal apps, set `terminal` in the config");
+        }
+    }
 
-    let apps = load_apps(&desktop_paths, &cfg.terminal);
-    cfg.apps.extend(apps);
+    for (alias, id) in &cfg.aliases {
+        match cfg.apps.iter_mut().find(|a| a.id() == id) {
+            Some(app) => app.aliases.push(alias.clone()),
+            None => eprintln!("Alias {alias} points to unknown app {id}"),
+        }
+    }
+
+    for id in cfg.launch.apps.keys() {
+        if !cfg.apps.iter().any(|a| a.id() == id) {
+            eprintln!("Launch options for unknown app {id}");
+        }
+    }
+
+    for app in &mut cfg.apps {
+        if let Some(options) = cfg.launch.apps.get(app.id()) {
+            app.launch = std::mem::take(&mut app.launch).or(options);
+        }
+        app.policy = cfg.launch.resolve(&app.launch);
+        app.policy.cwd = app.cwd.as_ref().and_then(misc::expand_tilde);
+        app.policy.env = env_changes(&cfg.environment);
+        app.policy.env.extend(env_changes(&app.env));
+        app.policy.clear_env = app.clear_env;
+    }
 
     Arc::new(cfg)
 }
 
-fn parse_desktop_file<P: AsRef<Path>>(filepath: P, term: &Option<String>) -> Option<AppEntry> {
-    let content = std::fs::read_to_string(filepath).ok()?;
+fn parse_desktop_file<P: AsRef<Path>>(filepath: P) -> Option<AppEntry> {
+    let content = std::fs::read_to_string(&filepath).ok()?;
 
     let start_idx = content.find("[Desktop Entry]")?;
     let section_start = start_idx + 15;
@@ -79,6 +563,10 @@ fn parse_desktop_file<P: AsRef<Path>>(filepath: P, term: &Option<String>) -> Opt
         "Type",
         "NoDisplay",
         "Terminal",
+        "Categories",
+        "Keywords",
+        "Path",
+        "StartupNotify",
     ];
 
     for line in section.lines() {
@@ -107,10 +595,6 @@ fn parse_desktop_file<P: AsRef<Path>>(filepath: P, term: &Option<String>) -> Opt
         .get("Terminal")
         .map_or(false, |v| v.eq_ignore_ascii_case("true"));
 
-    if term_app && term.is_none() {
-        return None;
-    }
-
     if fields.get("Type").map_or("", |v| v).to_string() != "Application" {
         return None;
     }
@@ -122,39 +606,66 @@ fn parse_desktop_file<P: AsRef<Path>>(filepath: P, term: &Option<String>) -> Opt
         return None;
     }
 
-    let cleaned_exec = exec_cmd
-        .replace(" %F", "")
-        .replace(" %f", "")
-        .replace(" %U", "")
-        .replace(" %u", "")
-        .replace("=%F", "")
-        .replace("=%f", "")
-        .replace("=%U", "")
-        .replace("=%u", "");
-
-    let cleaned_exec = if term_app {
-        format!("{} {cleaned_exec}", term.as_ref().unwrap())
-    } else {
-        cleaned_exec
-    };
+    let id = filepath
+        .as_ref()
+        .file_name()
+        .and_then(|f| f.to_str())
+        .map(str::to_owned);
 
     Some(AppEntry {
+        id,
         name: name.to_string(),
         gend: fields.get("GenericName").map_or(None, |v| Some(v.to_string())),
         desc: fields.get("Comment").map_or(None, |v| Some(v.to_string())),
         icon: fields.get("Icon").map_or(None, |v| Some(v.to_string())),
-        exec: cleaned_exec.to_string(),
+        exec: exec_cmd.to_string(),
+        aliases: Vec::new(),
+        categories: fields.get("Categories").map_or(Vec::new(), |v| split_list(v)),
+        keywords: fields.get("Keywords").map_or(Vec::new(), |v| split_list(v)),
+        cwd: fields.get("Path").filter(|p| !p.is_empty()).map(PathBuf::from),
+        env: HashMap::new(),
+        clear_env: false,
+        terminal: term_app,
+        startup_notify: fields.get("StartupNotify").map(|v| v.eq_ignore_ascii_case("true")),
+        launch: LaunchOptions::default(),
+        policy: LaunchPolicy::default(),
+        path: Some(filepath.as_ref().to_path_buf()),
     })
 }
 
-fn load_apps(desktop_paths: &[std::path::PathBuf], term: &Option<String>) -> Vec<AppEntry> {
+// `%%` is a literal `%`, not a code
+fn field_codes(exec: &str) -> Vec<char> {
+    let mut codes = Vec::new();
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            match chars.next() {
+                Some('%') | None => (),
+                Some(code) => codes.push(code),
+            }
+        }
+    }
+    codes
+}
+
+// Desktop entry lists are `;` separated, with an optional trailing `;`
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+fn load_apps(desktop_paths: &[std::path::PathBuf]) -> Vec<AppEntry> {
     let mut apps = Vec::new();
     for path in desktop_paths {
         if let Ok(entries) = std::fs::read_dir(path) {
             for entry in entries.flatten() {
                 if let Some(filename) = entry.file_name().to_str() {
                     if filename.ends_with(".desktop") {
-                        if let Some(desktop_app) = parse_desktop_file(entry.path(), &term) {
+                        if let Some(desktop_app) = parse_desktop_file(entry.path()) {
                             apps.push(desktop_app);
                         }
                     }
@@ -174,3 +685,88 @@ fn load_apps(desktop_paths: &[std::path::PathBuf], term: &Option<String>) -> Vec
 
     unique_apps
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dimension_reads_percent_and_pixels() {
+        assert_eq!(parse_dimension("30%", 1000), Some(300));
+        assert_eq!(parse_dimension("12.5 %", 1000), Some(125));
+        assert_eq!(parse_dimension("600px", 1000), Some(600));
+        assert_eq!(parse_dimension("600", 1000), Some(600));
+        assert_eq!(parse_dimension("wide", 1000), None);
+        assert_eq!(parse_dimension("%", 1000), None);
+    }
+
+    #[test]
+    fn dimension_resolve_clamps_and_falls_back() {
+        assert_eq!(Dimension::Pixels(500).resolve(1000), 500);
+        assert_eq!(Dimension::Pixels(5000).resolve(1000), 1000);
+        assert_eq!(Dimension::Pixels(-5).resolve(1000), 1);
+        assert_eq!(Dimension::Text(" 50% ".to_owned()).resolve(800), 400);
+        assert_eq!(Dimension::Text("150%".to_owned()).resolve(800), 800);
+        // invalid sizes are 40%
+        assert_eq!(Dimension::Text("big".to_owned()).resolve(1000), 400);
+    }
+
+    fn desktop_app(exec: &str) -> AppEntry {
+        let mut app: AppEntry = toml::from_str(&format!("name = \"Files\"\nicon = \"folder\"\nexec = {exec:?}")).unwrap();
+        app.path = Some(PathBuf::from("/usr/share/applications/files.desktop"));
+        app
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn field_codes_expand() {
+        assert_eq!(desktop_app("printf 100%%").commands(&[]), ["printf 100%"]);
+        assert_eq!(desktop_app("files %i").commands(&[]), ["files --icon 'folder'"]);
+        assert_eq!(
+            desktop_app("files --name %c").commands(&[]),
+            ["files --name 'Files'"]
+        );
+        assert_eq!(
+            desktop_app("files %k").commands(&[]),
+            ["files '/usr/share/applications/files.desktop'"]
+        );
+        // deprecated codes are dropped
+        assert_eq!(desktop_app("files %d %m --new").commands(&[]), ["files --new"]);
+    }
+
+    #[test]
+    fn field_codes_without_args_drop_their_word() {
+        assert_eq!(desktop_app("files %U").commands(&[]), ["files"]);
+        assert_eq!(
+            desktop_app("files --file=%f --new").commands(&[]),
+            ["files --new"]
+        );
+        assert_eq!(desktop_app("files --file=%f").commands(&[]), ["files"]);
+    }
+
+    #[test]
+    fn args_fill_the_field_codes() {
+        let two = args(&["a b", "it's"]);
+        assert_eq!(
+            desktop_app("files %F").commands(&two),
+            [r"files 'a b' 'it'\''s'"]
+        );
+        assert_eq!(
+            desktop_app("files --file=%f").commands(&two),
+            ["files --file='a b'", r"files --file='it'\''s'"]
+        );
+        // without a code they're appended
+        assert_eq!(desktop_app("files").commands(&two), [r"files 'a b' 'it'\''s'"]);
+    }
+
+    #[test]
+    fn config_apps_take_args_appended() {
+        let mut app = desktop_app("echo %f");
+        app.path = None;
+        assert_eq!(app.commands(&args(&["x", "y"])), ["echo %f 'x' 'y'"]);
+        assert_eq!(app.commands(&[]), ["echo %f"]);
+    }
+}
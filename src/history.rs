@@ -0,0 +1,220 @@
+use crate::gtk::glib;
+use crate::misc;
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+// history rotates to history.1 past this, so at most twice this is kept
+const MAX_BYTES: u64 = 512 * 1024;
+// of each output stream, from the end
+const EXCERPT_CHARS: usize = 2048;
+
+/// One finished (or failed to start) launch
+#[derive(Clone, Debug)]
+pub(crate) struct LaunchRecord {
+    /// Unix time it was launched
+    pub time: i64,
+    pub id: String,
+    pub command: String,
+    pub pid: Option<u32>,
+    pub failed: bool,
+    /// "exit 1", "signal 9", "spawn failed: ..."
+    pub outcome: String,
+    pub runtime_ms: u64,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl LaunchRecord {
+    pub fn time_str(&self) -> String {
+        glib::DateTime::from_unix_local(self.time)
+            .and_then(|d| d.format("%F %T"))
+            .map_or_else(|_| self.time.to_string(), |d| d.to_string())
+    }
+
+    /// One line for `gall log`
+    pub fn summary(&self) -> String {
+        format!(
+            "{}  {:<6} {:<24} {:<12} {}",
+            self.time_str(),
+            if self.failed { "FAIL" } else { "ok" },
+            self.id,
+            self.outcome,
+            self.command
+        )
+    }
+
+    fn to_line(&self) -> String {
+        let fields = [
+            self.time.to_string(),
+            escape(&self.id),
+            escape(&self.command),
+            self.pid.map_or(String::new(), |p| p.to_string()),
+            (self.failed as u8).to_string(),
+            escape(&self.outcome),
+            self.runtime_ms.to_string(),
+            escape(&excerpt(&self.stdout)),
+            escape(&excerpt(&self.stderr)),
+        ];
+        fields.join("\t")
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+
+        Some(Self {
+            time: fields.next()?.parse().ok()?,
+            id: unescape(fields.next()?),
+            command: unescape(fields.next()?),
+            pid: fields.next()?.parse().ok(),
+            failed: fields.next()? == "1",
+            outcome: unescape(fields.next()?),
+            runtime_ms: fields.next()?.parse().ok()?,
+            stdout: unescape(fields.next()?),
+            stderr: unescape(fields.next()?),
+        })
+    }
+}
+
+pub(crate) fn history_path() -> PathBuf {
+    misc::get_state_path("history")
+}
+
+fn rotated_path() -> PathBuf {
+    misc::get_state_path("history.1")
+}
+
+/// Append to the history file, moving it to `history.1` once it's too big
+pub(crate) fn append(record: &LaunchRecord) {
+    let path = history_path();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+
+    if std::fs::metadata(&path).is_ok_and(|m| m.len() > MAX_BYTES) {
+        let _ = std::fs::rename(&path, rotated_path());
+    }
+
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{}", record.to_line()));
+
+    if let Err(e) = written {
+        eprintln!("Failed to write history {}: {e}", path.display());
+    }
+}
+
+/// Every record still on disk, oldest first
+pub(crate) fn read_all() -> Vec<LaunchRecord> {
+    [rotated_path(), history_path()]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .flat_map(|data| data.lines().filter_map(LaunchRecord::from_line).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Records in `data`, for reading what was appended since last time
+pub(crate) fn parse(data: &str) -> Vec<LaunchRecord> {
+    data.lines().filter_map(LaunchRecord::from_line).collect()
+}
+
+fn excerpt(text: &str) -> String {
+    let skip = text.chars().count().saturating_sub(EXCERPT_CHARS);
+    text.chars().skip(skip).collect()
+}
+
+// one record per line, so tabs, newlines and backslashes are escaped
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> LaunchRecord {
+        LaunchRecord {
+            time: 1_700_000_000,
+            id: "firefox.desktop".to_owned(),
+            command: "firefox\t'a b'".to_owned(),
+            pid: Some(4242),
+            failed: true,
+            outcome: "exit 1".to_owned(),
+            runtime_ms: 250,
+            stdout: "line one\nline two\r\n".to_owned(),
+            stderr: r"C:\path\to\file".to_owned(),
+        }
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        let text = "tab\there\nnew\\line\r\\n";
+        assert_eq!(escape(text), r"tab\there\nnew\\line\r\\n");
+        assert_eq!(unescape(&escape(text)), text);
+        assert!(!escape(text).contains(['\t', '\n', '\r']));
+        // a trailing lone backslash is kept
+        assert_eq!(unescape("end\\"), "end\\");
+    }
+
+    #[test]
+    fn record_round_trips_through_a_line() {
+        let record = record();
+        let parsed = LaunchRecord::from_line(&record.to_line()).unwrap();
+        assert_eq!(format!("{parsed:?}"), format!("{record:?}"));
+
+        let no_pid = LaunchRecord {
+            pid: None,
+            ..record
+        };
+        assert_eq!(LaunchRecord::from_line(&no_pid.to_line()).unwrap().pid, None);
+    }
+
+    #[test]
+    fn from_line_skips_broken_lines() {
+        assert!(LaunchRecord::from_line("").is_none());
+        assert!(LaunchRecord::from_line("x\tid\tcmd\t1\t0\tok\t5\t\t").is_none());
+        assert!(LaunchRecord::from_line("1\tid\tcmd\t1\t0\tok").is_none());
+        assert_eq!(parse(&format!("garbage\n{}\n", record().to_line())).len(), 1);
+    }
+
+    #[test]
+    fn excerpt_keeps_the_end() {
+        let text = "é".repeat(EXCERPT_CHARS) + "end";
+        let kept = excerpt(&text);
+        assert_eq!(kept.chars().count(), EXCERPT_CHARS);
+        assert!(kept.ends_with("éend"));
+    }
+}
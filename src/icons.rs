@@ -0,0 +1,188 @@
+use crate::gtk;
+use crate::misc;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use gtk::prelude::*;
+use gtk::{gdk, gio, glib};
+
+const FALLBACK_ICON: &str = "application-x-executable";
+
+/// How a picker wants its icons, `size` is in logical pixels
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct IconStyle {
+    pub size: i32,
+    pub symbolic: bool,
+}
+
+// name or path, logical size, scale factor, symbolic
+type IconKey = (String, i32, i32, bool);
+
+#[derive(Default)]
+struct IconCache {
+    icons: HashMap<IconKey, gdk::Paintable>,
+    pending: HashMap<IconKey, Vec<glib::WeakRef<gtk::Image>>>,
+    on_invalidate: Vec<Box<dyn Fn()>>,
+    theme_hooked: bool,
+}
+
+// Pixbuf can't leave the thread it was made in, so the raw pixels do
+struct DecodedIcon {
+    width: i32,
+    height: i32,
+    stride: usize,
+    has_alpha: bool,
+    bytes: glib::Bytes,
+}
+
+thread_local! {
+    // GTK objects live on the main thread, and so does the cache
+    static CACHE: RefCell<IconCache> = RefCell::new(IconCache::default());
+}
+
+/// Set the icon on an existing image, rows are recycled so this must always overwrite
+///
+/// Theme icons and already decoded files are set right away, other files show a placeholder
+/// until a worker thread is done decoding them. Both are rendered for the image's scale factor,
+/// callers should set it again when that changes
+pub(crate) fn set_icon(image: &gtk::Image, icon_str: &str, style: IconStyle) {
+    hook_icon_theme();
+    image.set_pixel_size(style.size);
+
+    let scale = image.scale_factor().max(1);
+    let key: IconKey = (icon_str.to_owned(), style.size, scale, style.symbolic);
+    unsafe { image.set_data("icon-key", key.clone()) };
+
+    if let Some(paintable) = CACHE.with_borrow(|c| c.icons.get(&key).cloned()) {
+        image.set_paintable(Some(&paintable));
+        return;
+    }
+
+    let path = misc::expand_tilde(icon_str).expect("could not expand path");
+    if path.is_file() {
+        image.set_icon_name(Some(FALLBACK_ICON));
+        queue_file_icon(key, path, image);
+        return;
+    }
+
+    let paintable = theme_icon(&key);
+    if let Some(paintable) = &paintable {
+        CACHE.with_borrow_mut(|c| c.icons.insert(key, paintable.clone()));
+    }
+    image.set_paintable(paintable.as_ref());
+}
+
+/// Called after the cache is dropped, so bound rows can set their icons again
+pub(crate) fn connect_invalidated<F: Fn() + 'static>(callback: F) {
+    CACHE.with_borrow_mut(|c| c.on_invalidate.push(Box::new(callback)));
+}
+
+// Scalable (SVG) theme icons are rendered at the final size, so passing the scale is enough
+fn theme_icon((icon_str, size, scale, symbolic): &IconKey) -> Option<gdk::Paintable> {
+    let display = gdk::Display::default()?;
+    let icon_theme = gtk::IconTheme::for_display(&display);
+
+    let mut flags = gtk::IconLookupFlags::PRELOAD;
+    if *symbolic {
+        flags |= gtk::IconLookupFlags::FORCE_SYMBOLIC;
+    }
+
+    let icon_paintable = icon_theme.lookup_icon(
+        icon_str,
+        &[FALLBACK_ICON], // bad?
+        *size,
+        *scale,
+        gtk::TextDirection::None,
+        flags,
+    );
+
+    Some(icon_paintable.upcast())
+}
+
+fn queue_file_icon(key: IconKey, path: PathBuf, image: &gtk::Image) {
+    let already_queued = CACHE.with_borrow_mut(|c| {
+        let waiting = c.pending.entry(key.clone()).or_default();
+        waiting.push(image.downgrade());
+        waiting.len() > 1
+    });
+
+    if already_queued {
+        return;
+    }
+
+    // device pixels, the image scales it back down to its logical size
+    let size = key.1 * key.2;
+    glib::spawn_future_local(async move {
+        let decoded = gio::spawn_blocking(move || decode_file_icon(&path, size)).await;
+
+        let paintable = match decoded {
+            Ok(Some(icon)) => Some(icon.into_texture().upcast()),
+            _ => theme_icon(&key),
+        };
+
+        let waiting = CACHE.with_borrow_mut(|c| {
+            if let Some(paintable) = &paintable {
+                c.icons.insert(key.clone(), paintable.clone());
+            }
+            c.pending.remove(&key).unwrap_or_default()
+        });
+
+        for image in waiting.iter().filter_map(|w| w.upgrade()) {
+            // the row may have been rebound to another app meanwhile
+            let wanted = unsafe { image.data::<IconKey>("icon-key").map(|k| k.as_ref().clone()) };
+            if wanted.as_ref() == Some(&key) {
+                image.set_paintable(paintable.as_ref());
+            }
+        }
+    });
+}
+
+fn decode_file_icon(path: &PathBuf, size: i32) -> Option<DecodedIcon> {
+    let pixbuf = gdk_pixbuf::Pixbuf::from_file_at_scale(path, size, size, true).ok()?;
+
+    Some(DecodedIcon {
+        width: pixbuf.width(),
+        height: pixbuf.height(),
+        stride: pixbuf.rowstride() as usize,
+        has_alpha: pixbuf.has_alpha(),
+        bytes: pixbuf.read_pixel_bytes(),
+    })
+}
+
+impl DecodedIcon {
+    fn into_texture(self) -> gdk::Texture {
+        let format = if self.has_alpha {
+            gdk::MemoryFormat::R8g8b8a8
+        } else {
+            gdk::MemoryFormat::R8g8b8
+        };
+
+        gdk::MemoryTexture::new(self.width, self.height, format, &self.bytes, self.stride).upcast()
+    }
+}
+
+fn hook_icon_theme() {
+    if CACHE.with_borrow(|c| c.theme_hooked) {
+        return;
+    }
+
+    let Some(display) = gdk::Display::default() else {
+        return;
+    };
+
+    gtk::IconTheme::for_display(&display).connect_changed(|_| {
+        CACHE.with_borrow_mut(|c| c.icons.clear());
+
+        // callbacks may rebind rows, which calls back into the cache
+        let callbacks = CACHE.with_borrow_mut(|c| std::mem::take(&mut c.on_invalidate));
+        callbacks.iter().for_each(|f| f());
+        CACHE.with_borrow_mut(|c| {
+            let added = std::mem::replace(&mut c.on_invalidate, callbacks);
+            c.on_invalidate.extend(added);
+        });
+    });
+
+    CACHE.with_borrow_mut(|c| c.theme_hooked = true);
+}
@@ -0,0 +1,265 @@
+use crate::gtk::gdk;
+
+use std::collections::HashMap;
+
+/// Things a key chord can do in a picker
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    SelectNext,
+    SelectPrev,
+    /// Grid only, lets the entry have Left/Right in the list
+    SelectLeft,
+    SelectRight,
+    PageDown,
+    PageUp,
+    First,
+    Last,
+    Accept,
+    AcceptKeepOpen,
+    /// Run in the terminal, even GUI apps
+    AcceptTerminal,
+    /// Open the desktop file in `$EDITOR`
+    EditEntry,
+    Cancel,
+    ToggleMode,
+    /// Clear the input, or toggle the search mode if it's already empty
+    Clear,
+    DeleteWord,
+    TogglePin,
+    TogglePreview,
+    /// SIGTERM the selected app's running launches
+    Terminate,
+    /// SIGKILL them
+    Kill,
+    ShowLog,
+}
+
+impl Action {
+    const NAMES: [(&'static str, Action); 21] = [
+        ("select-next", Action::SelectNext),
+        ("select-prev", Action::SelectPrev),
+        ("select-left", Action::SelectLeft),
+        ("select-right", Action::SelectRight),
+        ("page-down", Action::PageDown),
+        ("page-up", Action::PageUp),
+        ("first", Action::First),
+        ("last", Action::Last),
+        ("accept", Action::Accept),
+        ("accept-keep-open", Action::AcceptKeepOpen),
+        ("accept-terminal", Action::AcceptTerminal),
+        ("edit-entry", Action::EditEntry),
+        ("cancel", Action::Cancel),
+        ("toggle-mode", Action::ToggleMode),
+        ("clear", Action::Clear),
+        ("delete-word", Action::DeleteWord),
+        ("toggle-pin", Action::TogglePin),
+        ("toggle-preview", Action::TogglePreview),
+        ("terminate", Action::Terminate),
+        ("kill", Action::Kill),
+        ("show-log", Action::ShowLog),
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter().find(|(n, _)| *n == name).map(|(_, a)| *a)
+    }
+}
+
+const DEFAULT_KEYS: &[(&str, &str)] = &[
+    ("Up", "select-prev"),
+    ("Down", "select-next"),
+    ("Left", "select-left"),
+    ("Right", "select-right"),
+    ("Page_Up", "page-up"),
+    ("Page_Down", "page-down"),
+    ("Ctrl+Home", "first"),
+    ("Ctrl+End", "last"),
+    ("Return", "accept"),
+    ("KP_Enter", "accept"),
+    ("Ctrl+Return", "accept-keep-open"),
+    ("Shift+Return", "accept-terminal"),
+    ("Alt+Return", "edit-entry"),
+    ("Escape", "cancel"),
+    ("Ctrl+Escape", "clear"),
+    ("Alt+m", "toggle-mode"),
+    ("Ctrl+w", "delete-word"),
+    ("Alt+p", "toggle-pin"),
+    ("Alt+i", "toggle-preview"),
+    ("Alt+Delete", "terminate"),
+    ("Alt+Shift+Delete", "kill"),
+    ("Alt+l", "show-log"),
+];
+
+const EMACS_KEYS: &[(&str, &str)] = &[
+    ("Ctrl+n", "select-next"),
+    ("Ctrl+p", "select-prev"),
+    ("Ctrl+b", "select-left"),
+    ("Ctrl+f", "select-right"),
+    ("Ctrl+v", "page-down"),
+    ("Alt+v", "page-up"),
+    ("Alt+less", "first"),
+    ("Alt+greater", "last"),
+    ("Ctrl+g", "cancel"),
+    ("Ctrl+u", "clear"),
+];
+
+const VIM_KEYS: &[(&str, &str)] = &[
+    ("Ctrl+j", "select-next"),
+    ("Ctrl+k", "select-prev"),
+    ("Ctrl+h", "select-left"),
+    ("Ctrl+l", "select-right"),
+    ("Ctrl+d", "page-down"),
+    ("Ctrl+u", "page-up"),
+    ("Ctrl+bracketleft", "cancel"),
+];
+
+type Chord = (gdk::Key, gdk::ModifierType);
+
+/// Key chords to actions, the preset first and then `[keys]` from the config on top
+#[derive(Default)]
+pub(crate) struct Keymap {
+    bindings: HashMap<Chord, Action>,
+}
+
+impl Keymap {
+    pub fn new(preset: &str, keys: &HashMap<String, String>) -> Self {
+        let mut keymap = Self::default();
+        keymap.bind_all(DEFAULT_KEYS.iter().copied());
+
+        match preset {
+            "default" => (),
+            "emacs" => keymap.bind_all(EMACS_KEYS.iter().copied()),
+            "vim" => keymap.bind_all(VIM_KEYS.iter().copied()),
+            other => eprintln!("Unknown keymap preset {other}, using default"),
+        }
+
+        keymap.bind_all(keys.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        keymap
+    }
+
+    pub fn lookup(&self, keyval: gdk::Key, state: gdk::ModifierType) -> Option<Action> {
+        let mods = state & mods_mask();
+        let key = keyval.to_lower();
+
+        // Shift is often just what made the symbol (Alt+< is Alt+Shift+less)
+        self.bindings
+            .get(&(key, mods))
+            .or_else(|| self.bindings.get(&(key, mods - gdk::ModifierType::SHIFT_MASK)))
+            .copied()
+    }
+
+    // "none" unbinds, so presets can be trimmed from the config
+    fn bind_all<'a>(&mut self, keys: impl Iterator<Item = (&'a str, &'a str)>) {
+        for (chord, action) in keys {
+            let Some(chord) = parse_chord(chord) else {
+                eprintln!("Invalid key chord: {chord}");
+                continue;
+            };
+
+            if action == "none" {
+                self.bindings.remove(&chord);
+                continue;
+            }
+
+            match Action::from_name(action) {
+                Some(action) => {
+                    self.bindings.insert(chord, action);
+                }
+                None => eprintln!("Unknown action: {action}"),
+            }
+        }
+    }
+}
+
+/// Modifiers that bindings care about, anything else (like Num Lock) is ignored
+pub(crate) fn mods_mask() -> gdk::ModifierType {
+    gdk::ModifierType::CONTROL_MASK
+        | gdk::ModifierType::ALT_MASK
+        | gdk::ModifierType::SHIFT_MASK
+        | gdk::ModifierType::SUPER_MASK
+}
+
+pub(crate) fn parse_modifier(name: &str) -> Option<gdk::ModifierType> {
+    match name.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => Some(gdk::ModifierType::CONTROL_MASK),
+        "alt" => Some(gdk::ModifierType::ALT_MASK),
+        "shift" => Some(gdk::ModifierType::SHIFT_MASK),
+        "super" => Some(gdk::ModifierType::SUPER_MASK),
+        _ => None,
+    }
+}
+
+/// `Ctrl+Shift+j` style, key names are GDK's (`Return`, `Page_Down`, `less`, ...)
+fn parse_chord(chord: &str) -> Option<Chord> {
+    let mut parts: Vec<&str> = chord.split('+').map(str::trim).collect();
+    let key = parts.pop().filter(|k| !k.is_empty())?;
+
+    let mut mods = gdk::ModifierType::empty();
+    for part in parts {
+        mods |= parse_modifier(part)?;
+    }
+
+    let key = match key {
+        "Enter" => "Return",
+        "Esc" => "Escape",
+        key => key,
+    };
+
+    Some((gdk::Key::from_name(key)?.to_lower(), mods))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gdk::ModifierType as M;
+
+    #[test]
+    fn parse_chord_reads_modifiers_and_aliases() {
+        assert_eq!(
+            parse_chord("Ctrl+Shift+j"),
+            Some((gdk::Key::j, M::CONTROL_MASK | M::SHIFT_MASK))
+        );
+        assert_eq!(
+            parse_chord("alt + Enter"),
+            Some((gdk::Key::Return, M::ALT_MASK))
+        );
+        assert_eq!(parse_chord("Esc"), Some((gdk::Key::Escape, M::empty())));
+        // keys are stored lowercase, Shift says if it was upper
+        assert_eq!(parse_chord("Alt+M"), Some((gdk::Key::m, M::ALT_MASK)));
+    }
+
+    #[test]
+    fn parse_chord_rejects_bad_chords() {
+        assert_eq!(parse_chord("Hyper+j"), None);
+        assert_eq!(parse_chord("Ctrl+"), None);
+        assert_eq!(parse_chord("Ctrl+NoSuchKey"), None);
+    }
+
+    #[test]
+    fn lookup_falls_back_to_the_chord_without_shift() {
+        let keys = HashMap::from([("Alt+less".to_owned(), "first".to_owned())]);
+        let keymap = Keymap::new("default", &keys);
+
+        assert_eq!(
+            keymap.lookup(gdk::Key::less, M::ALT_MASK | M::SHIFT_MASK),
+            Some(Action::First)
+        );
+        // an exact Shift binding wins over the fallback
+        assert_eq!(
+            keymap.lookup(gdk::Key::Return, M::SHIFT_MASK),
+            Some(Action::AcceptTerminal)
+        );
+        assert_eq!(
+            keymap.lookup(gdk::Key::Return, M::LOCK_MASK),
+            Some(Action::Accept)
+        );
+        assert_eq!(keymap.lookup(gdk::Key::less, M::CONTROL_MASK), None);
+    }
+
+    #[test]
+    fn none_unbinds_a_preset_key() {
+        let keys = HashMap::from([("Alt+m".to_owned(), "none".to_owned())]);
+        let keymap = Keymap::new("default", &keys);
+        assert_eq!(keymap.lookup(gdk::Key::m, M::ALT_MASK), None);
+    }
+}
@@ -0,0 +1,377 @@
+use crate::config::{AppEntry, LaunchPolicy};
+use crate::gtk;
+use crate::history::{self, LaunchRecord};
+use crate::misc::{self, CommandError};
+use crate::running::Running;
+
+use std::cell::Cell;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use gtk::prelude::*;
+use gtk::{gdk, gio, glib};
+
+// stdout + stderr files kept in the logs directory
+const KEEP_LOGS: usize = 200;
+// most of the log the error window gets, from the end
+const LOG_EXCERPT: u64 = 16 * 1024;
+// a log past this is cut down to its end while the app runs, checked every LOG_CHECK seconds
+const LOG_LIMIT: u64 = 4 * 1024 * 1024;
+const LOG_CHECK: u32 = 5;
+
+/// A process started by `spawn`, output goes to its own pair of log files unless discarded
+pub(crate) struct Launch {
+    pub pid: u32,
+    pub started: Instant,
+    /// Unix time, for the history
+    pub time: i64,
+    pub stdout: Option<PathBuf>,
+    pub stderr: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Exit {
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+    pub runtime: Duration,
+}
+
+impl Exit {
+    fn from_wait_status(status: i32, runtime: Duration) -> Self {
+        let (code, signal) = if libc::WIFEXITED(status) {
+            (Some(libc::WEXITSTATUS(status)), None)
+        } else if libc::WIFSIGNALED(status) {
+            (None, Some(libc::WTERMSIG(status)))
+        } else {
+            (None, None)
+        };
+
+        Self { code, signal, runtime }
+    }
+
+    pub fn is_success(&self, codes: &[i32]) -> bool {
+        self.code.is_some_and(|code| codes.contains(&code))
+    }
+
+    pub fn describe(&self) -> String {
+        match (self.code, self.signal) {
+            (Some(code), _) => format!("exit code {code}"),
+            (_, Some(signal)) => format!("signal {signal}"),
+            _ => "unknown status".to_owned(),
+        }
+    }
+}
+
+impl Launch {
+    /// Error for the error window, with the end of both logs
+    pub fn failure(&self, exit: &Exit) -> CommandError {
+        CommandError {
+            reason: format!("Command failed with {}", exit.describe()),
+            stdout: self.stdout.as_deref().and_then(read_excerpt),
+            stderr: self.stderr.as_deref().and_then(read_excerpt),
+        }
+    }
+}
+
+/// Launch `exec` for app `id`: tracked in `running`, added to the history once it exits, and an
+/// error window if it fails within `policy.failure_timeout`. `on_change` runs after it starts
+/// and after it exits
+pub(crate) fn launch<F>(
+    gapp: &gtk::Application,
+    running: &Arc<Mutex<Running>>,
+    id: &str,
+    exec: &str,
+    policy: &LaunchPolicy,
+    on_change: F,
+) where
+    F: Fn() + 'static,
+{
+    let on_change = Rc::new(on_change);
+
+    let launched = {
+        let (gapp, running, on_change) = (gapp.clone(), running.clone(), on_change.clone());
+        let (record_id, record_exec) = (id.to_owned(), exec.to_owned());
+        let judge = policy.clone();
+
+        spawn(exec, id, policy, move |launch, exit| {
+            let success = exit.is_success(&judge.success_codes);
+            running.lock().unwrap().exited(launch.pid, exit);
+            history::append(&LaunchRecord {
+                time: launch.time,
+                id: record_id,
+                command: record_exec,
+                pid: Some(launch.pid),
+                failed: !success,
+                outcome: exit.describe(),
+                runtime_ms: exit.runtime.as_millis() as u64,
+                stdout: launch.stdout.as_deref().and_then(read_excerpt).unwrap_or_default(),
+                stderr: launch.stderr.as_deref().and_then(read_excerpt).unwrap_or_default(),
+            });
+            on_change();
+
+            // later exits are the app closing, not failing to start
+            if !success && exit.runtime < judge.failure_timeout {
+                if let Some(startup_id) = &judge.startup_id {
+                    startup_failed(startup_id);
+                }
+                if judge.show_error {
+                    crate::blocks::create_error_window(&gapp, launch.failure(&exit));
+                }
+            }
+        })
+    };
+
+    match launched {
+        Ok(launch) => {
+            running.lock().unwrap().add(launch.pid, id, exec);
+            on_change();
+        }
+        Err(error) => {
+            history::append(&LaunchRecord {
+                time: unix_now(),
+                id: id.to_owned(),
+                command: exec.to_owned(),
+                pid: None,
+                failed: true,
+                outcome: error.reason.clone(),
+                runtime_ms: 0,
+                stdout: String::new(),
+                stderr: String::new(),
+            });
+            if policy.show_error {
+                crate::blocks::create_error_window(gapp, error);
+            }
+        }
+    }
+}
+
+/// Token for launching `app` through GDK's launch context, an XDG activation token on Wayland
+/// and a DESKTOP_STARTUP_ID on X11, so its window gets the focus
+///
+/// `StartupNotify=false` opts out. Unset only asks on Wayland, where the token is just the
+/// permission to take the focus; on X11 it would leave a busy cursor for apps that never answer
+///
+/// GDK builds the Wayland token from the focused surface, so ask before hiding the window
+pub(crate) fn startup_id(app: &AppEntry) -> Option<String> {
+    let info = app
+        .path
+        .as_ref()
+        .and_then(gio::DesktopAppInfo::from_filename)
+        .map(|info| info.upcast::<gio::AppInfo>());
+
+    request_startup_id(app.startup_notify, info, &app.exec, &app.name)
+}
+
+/// `startup_id` for a command that isn't an app, like the editor or a rerun from the log
+pub(crate) fn command_startup_id(exec: &str, name: &str) -> Option<String> {
+    request_startup_id(None, None, exec, name)
+}
+
+fn request_startup_id(notify: Option<bool>, info: Option<gio::AppInfo>, exec: &str, name: &str) -> Option<String> {
+    let display = gdk::Display::default()?;
+    let wayland = display.type_().name() == "GdkWaylandDisplay";
+    if !notify.unwrap_or(wayland) {
+        return None;
+    }
+
+    let flags = gio::AppInfoCreateFlags::SUPPORTS_STARTUP_NOTIFICATION;
+    let info = info.or_else(|| gio::AppInfo::create_from_commandline(exec, Some(name), flags).ok())?;
+
+    display
+        .app_launch_context()
+        .startup_notify_id(Some(&info), &[])
+        .map(Into::into)
+}
+
+// lets the X11 startup sequence end now instead of timing out
+fn startup_failed(startup_id: &str) {
+    if let Some(display) = gdk::Display::default() {
+        display.app_launch_context().launch_failed(startup_id);
+    }
+}
+
+/// Run `exec` with `sh -c` in its own session, `on_exit` runs on the main thread once reaped
+///
+/// `name` only names the log files, output goes to /dev/null unless `policy` captures it. The
+/// child stays ours so GLib can reap it, but it's out of our process group and session, so it
+/// outlives the daemon and ignores its terminal
+pub(crate) fn spawn<F>(exec: &str, name: &str, policy: &LaunchPolicy, on_exit: F) -> Result<Launch, CommandError>
+where
+    F: FnOnce(&Launch, Exit) + 'static,
+{
+    let spawn_error = |e: std::io::Error| CommandError {
+        reason: format!("Failed to spawn process: {}", e),
+        stderr: None,
+        stdout: None,
+    };
+
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(exec).stdin(Stdio::null());
+
+    if policy.clear_env {
+        command.env_clear();
+    }
+    for (name, value) in &policy.env {
+        match value {
+            Some(value) => command.env(name, value),
+            None => command.env_remove(name),
+        };
+    }
+
+    // GLib sets both whatever the backend, apps read the one they know
+    if let Some(startup_id) = &policy.startup_id {
+        command.env("DESKTOP_STARTUP_ID", startup_id).env("XDG_ACTIVATION_TOKEN", startup_id);
+    }
+
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    if let Some(cwd) = policy.cwd.as_ref().or(home.as_ref()) {
+        command.current_dir(cwd);
+    }
+
+    let (stdout, stderr) = if policy.capture_output {
+        let (stdout, stderr) = log_paths(name);
+        command
+            .stdout(create_log(&stdout).map_err(spawn_error)?)
+            .stderr(create_log(&stderr).map_err(spawn_error)?);
+        (Some(stdout), Some(stderr))
+    } else {
+        command.stdout(Stdio::null()).stderr(Stdio::null());
+        (None, None)
+    };
+
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+
+    let child = command.spawn().map_err(|e| {
+        if let Some(startup_id) = &policy.startup_id {
+            startup_failed(startup_id);
+        }
+        spawn_error(e)
+    })?;
+    let launch = Launch {
+        pid: child.id(),
+        started: Instant::now(),
+        time: unix_now(),
+        stdout,
+        stderr,
+    };
+    // dropping `Child` neither waits nor kills, the child watch below reaps it
+    drop(child);
+
+    let watched = Launch {
+        stdout: launch.stdout.clone(),
+        stderr: launch.stderr.clone(),
+        ..launch
+    };
+    let alive = Rc::new(Cell::new(true));
+    if let (Some(stdout), Some(stderr)) = (launch.stdout.clone(), launch.stderr.clone()) {
+        let alive = alive.clone();
+        glib::timeout_add_seconds_local(LOG_CHECK, move || {
+            if !alive.get() {
+                return glib::ControlFlow::Break;
+            }
+            cap_log(&stdout);
+            cap_log(&stderr);
+            glib::ControlFlow::Continue
+        });
+    }
+
+    let mut on_exit = Some(on_exit);
+    glib::child_watch_add_local(glib::Pid(launch.pid as i32), move |_, status| {
+        alive.set(false);
+        let exit = Exit::from_wait_status(status, watched.started.elapsed());
+        if let Some(on_exit) = on_exit.take() {
+            on_exit(&watched, exit);
+        }
+    });
+
+    prune_logs();
+    Ok(launch)
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+fn logs_dir() -> PathBuf {
+    misc::get_state_path("logs")
+}
+
+fn log_paths(name: &str) -> (PathBuf, PathBuf) {
+    let dir = logs_dir();
+    let _ = std::fs::create_dir_all(&dir);
+
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "._-".contains(c) { c } else { '_' })
+        .collect();
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+
+    let base = format!("{stamp}-{name}");
+    (dir.join(format!("{base}.out")), dir.join(format!("{base}.err")))
+}
+
+// appending, so writes land at the end again after `cap_log` truncates
+fn create_log(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+// keeps the end, what the error window and history show
+fn cap_log(path: &Path) {
+    if !std::fs::metadata(path).is_ok_and(|m| m.len() > LOG_LIMIT) {
+        return;
+    }
+
+    let tail = read_excerpt(path).unwrap_or_default();
+    let Ok(mut file) = create_log(path) else {
+        return;
+    };
+    if file.set_len(0).is_ok() {
+        let _ = write!(file, "[gall: log over {} MiB, cut to its end]\n{tail}", LOG_LIMIT / 1024 / 1024);
+    }
+}
+
+// timestamps lead the names, so sorting them is oldest first
+fn prune_logs() {
+    let Ok(entries) = std::fs::read_dir(logs_dir()) else {
+        return;
+    };
+
+    let mut logs: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    if logs.len() <= KEEP_LOGS {
+        return;
+    }
+
+    logs.sort();
+    for old in &logs[..logs.len() - KEEP_LOGS] {
+        let _ = std::fs::remove_file(old);
+    }
+}
+
+fn read_excerpt(path: &Path) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    if len > LOG_EXCERPT {
+        file.seek(SeekFrom::Start(len - LOG_EXCERPT)).ok()?;
+    }
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
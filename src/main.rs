@@ -1,14 +1,27 @@
 mod args;
 mod blocks;
 mod config;
+mod history;
+mod icons;
+mod keymap;
+mod launcher;
 mod misc;
 mod pickers;
+mod pins;
+mod placement;
+mod running;
 mod socket;
+mod stats;
+mod styles;
+mod terminal;
+mod watch;
 
 use gtk4 as gtk;
 
 use clap::Parser;
+use std::cell::RefCell;
 use std::path::PathBuf;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 use gtk::gio::ApplicationFlags;
@@ -17,6 +30,9 @@ use gtk::{glib, Application, ApplicationWindow};
 
 use config::ConfigLoad;
 use pickers::{Picker, PickerKind};
+use pins::Pins;
+use running::Running;
+use stats::LaunchStats;
 use socket::AppMessage;
 
 type PickerCurr = Arc<Mutex<Option<Arc<dyn Picker>>>>;
@@ -35,6 +51,8 @@ struct AppState {
     styles_path: PathBuf,
     msg_queue: socket::MessageQueue,
     config: Arc<ConfigLoad>,
+    /// Set by `gall theme`, wins over the config until restart
+    theme: Option<String>,
 }
 
 impl AppState {
@@ -44,8 +62,13 @@ impl AppState {
             styles_path,
             msg_queue,
             config,
+            theme: None,
         }
     }
+
+    fn theme(&self) -> Option<&str> {
+        self.theme.as_deref().or(self.config.theme.as_deref())
+    }
 }
 
 struct GallApp {
@@ -54,35 +77,71 @@ struct GallApp {
     window: Arc<ApplicationWindow>,
     pickers: PickerList,
     picker: PickerCurr,
+    pins: Arc<Mutex<Pins>>,
+    stats: Arc<Mutex<LaunchStats>>,
+    running: Arc<Mutex<Running>>,
+    watcher: RefCell<Option<Rc<watch::Watcher>>>,
 }
 
 impl GallApp {
     pub fn new(app: &Application, state: Arc<Mutex<AppState>>) -> Self {
-        let (w, h) = misc::get_full_display_size();
-        let (w, h) = (w as f32 * 0.3, h as f32 * 0.4);
+        let pins = Pins::load(&state.lock().unwrap().config.pinned);
 
         let window = ApplicationWindow::builder()
             .application(app)
             .title("Gall")
-            .default_width(w as i32)
-            .default_height(h as i32)
             .decorated(false)
             .build();
 
+        #[cfg(feature = "layer-shell")]
+        placement::init_layer_shell(&window);
+        placement::warn_unplaced(&window, &state.lock().unwrap().config.window);
+        placement::place(&window, &state.lock().unwrap().config.window);
+
         Self {
             app: app.clone(),
             state,
             window: Arc::new(window),
             pickers: Arc::new(Mutex::new(Vec::with_capacity(PickerKind::None as usize))),
             picker: Arc::new(Mutex::new(None)),
+            pins: Arc::new(Mutex::new(pins)),
+            stats: Arc::new(Mutex::new(LaunchStats::load())),
+            running: Arc::new(Mutex::new(Running::default())),
+            watcher: RefCell::new(None),
         }
     }
 
+    /// Open the launch log viewer, where "Run again" counts as launching that app
+    fn show_log(self: &Arc<Self>) {
+        let gall = self.clone();
+        blocks::create_log_window(&self.app, move |record| {
+            let (mut policy, app) = {
+                let state = gall.state.lock().unwrap();
+                let app = state.config.apps.iter().find(|a| a.id() == record.id).cloned();
+                (state.config.launch_policy(&record.id), app)
+            };
+            // the log window has the focus, so the token can pass it on
+            policy.startup_id = match &app {
+                Some(app) => launcher::startup_id(app),
+                None => launcher::command_startup_id(&record.command, &record.id),
+            };
+            if app.is_some() {
+                gall.stats.lock().unwrap().record(&record.id);
+            }
+
+            let pickers = gall.pickers.clone();
+            launcher::launch(&gall.app, &gall.running, &record.id, &record.command, &policy, move || {
+                pickers.lock().unwrap().iter().for_each(|p| p.update_running());
+            });
+        });
+    }
+
     pub fn load(&self, app: Arc<GallApp>) -> &Self {
         {
             let state = self.state.clone();
             let locked = state.lock().unwrap();
-            misc::apply_styles(&locked.styles_path);
+            styles::set_theme(locked.theme());
+            styles::apply_styles(&locked.styles_path);
 
             let mut pickers_lock = self.pickers.lock().unwrap();
 
@@ -96,7 +155,10 @@ impl GallApp {
             }
 
             let write_queue = locked.msg_queue.clone();
-            std::thread::spawn(move || socket::start_socket_listener(write_queue));
+            let running = self.running.clone();
+            let pins = self.pins.clone();
+            let state = self.state.clone();
+            std::thread::spawn(move || socket::start_socket_listener(write_queue, running, pins, state));
             println!(
                 "🔌Starting socket listener on {}",
                 socket::get_socket_path()
@@ -114,7 +176,9 @@ impl GallApp {
             let window = self.window.clone();
             let picker = self.picker.clone();
             let pickers = self.pickers.clone();
+            let pins = self.pins.clone();
             let gtk_app = self.app.clone();
+            let gall = app.clone();
 
             glib::timeout_add_local(std::time::Duration::from_millis(16), move || {
                 let Ok(mut queue) = queue_for_idle.lock() else {
@@ -125,45 +189,107 @@ impl GallApp {
                     return glib::ControlFlow::Continue;
                 };
 
-                let message = AppMessage::from(message);
+                let message = match AppMessage::try_from(message.as_slice()) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        eprintln!("Bad message in queue: {e}");
+                        return glib::ControlFlow::Continue;
+                    }
+                };
                 println!("📨Got Message: {message:?}");
                 match message {
                     AppMessage::TogglePicker(kind) => {
                         let locked = state.lock().unwrap();
 
-                        if locked.config.css_reload {
-                            misc::apply_styles(&locked.styles_path);
-                        }
-
                         if window.is_visible() {
                             window.hide();
                             return glib::ControlFlow::Continue;
                         }
 
                         picker_switch(&pickers, &picker, kind);
+                        placement::place(&window, &locked.config.window);
                         window.show();
                     }
                     AppMessage::AppReload => {
-                        let mut locked = state.lock().unwrap();
-                        misc::apply_styles(&locked.styles_path);
-                        locked.config = config::load_config(&locked.config_path);
-
-                        let pickers_lock = pickers.lock().unwrap();
-                        for it in &*pickers_lock {
-                            it.reload(&locked.config);
-                        }
+                        reload_config(&state, &pins, &pickers);
+                        reload_styles(&state);
                     }
                     AppMessage::AppClose => {
                         let _ = std::fs::remove_file(socket::get_socket_path());
                         gtk_app.quit();
                     }
-                    AppMessage::AppPing => (), // listener handles this
+                    // the listener already changed the pins and answered the CLI
+                    AppMessage::PinApp(_) | AppMessage::UnpinApp(_) => {
+                        pickers.lock().unwrap().iter().for_each(|it| it.refresh());
+                    }
+                    AppMessage::SetTheme(name) => {
+                        let mut locked = state.lock().unwrap();
+                        styles::set_theme(Some(&name));
+                        locked.theme = Some(name);
+                    }
+                    AppMessage::ShowLog => gall.show_log(),
+                    AppMessage::AppPing | AppMessage::ListRunning => (), // listener handles these
                 }
 
                 glib::ControlFlow::Continue
             });
         }
 
+        {
+            let state = self.state.clone();
+            placement::follow_monitor(&self.window, move || state.lock().unwrap().config.window.clone());
+        }
+
+        {
+            let locked = self.state.lock().unwrap();
+
+            if locked.config.watch {
+                let state = self.state.clone();
+                let pins = self.pins.clone();
+                let pickers = self.pickers.clone();
+
+                let watcher = watch::Watcher::new(&locked.config_path, &locked.styles_path, move |changed| {
+                    println!("📝Reloading {changed:?}");
+                    match changed {
+                        watch::Changed::Config => reload_config(&state, &pins, &pickers),
+                        watch::Changed::Styles => reload_styles(&state),
+                    }
+                });
+                self.watcher.replace(Some(watcher));
+            }
+        }
+
+        {
+            let state = self.state.clone();
+            let picker = self.picker.clone();
+
+            self.window.connect_is_active_notify(move |window| {
+                if window.is_active() {
+                    return;
+                }
+
+                let (window, state, picker) = (window.clone(), state.clone(), picker.clone());
+
+                // focus can bounce around while the window is shown or hidden, and the
+                // toggle handler may be holding the state
+                glib::idle_add_local_once(move || {
+                    if window.is_active() || !window.is_visible() {
+                        return;
+                    }
+
+                    if !state.lock().unwrap().config.window.hide_on_focus_loss {
+                        return;
+                    }
+
+                    let current = picker.lock().unwrap().clone();
+                    match current {
+                        Some(picker) => picker.cancel(),
+                        None => window.hide(),
+                    }
+                });
+            });
+        }
+
         {
             let window = self.window.clone();
 
@@ -177,6 +303,29 @@ impl GallApp {
     }
 }
 
+/// Re-read the config, keeping the current one if the new one doesn't parse
+fn reload_config(state: &Mutex<AppState>, pins: &Mutex<Pins>, pickers: &PickerList) {
+    let mut locked = state.lock().unwrap();
+    let Some(config) = config::reload_config(&locked.config_path) else {
+        eprintln!("Keeping the previous config");
+        return;
+    };
+
+    locked.config = config;
+    styles::set_theme(locked.theme());
+    pins.lock().unwrap().set_config(&locked.config.pinned);
+
+    let pickers_lock = pickers.lock().unwrap();
+    for it in &*pickers_lock {
+        it.reload(&locked.config);
+    }
+}
+
+fn reload_styles(state: &Mutex<AppState>) {
+    let locked = state.lock().unwrap();
+    styles::apply_styles(&locked.styles_path);
+}
+
 fn picker_switch(pickers: &PickerList, picker: &PickerCurr, kind: PickerKind) {
     let mut picker_lock = picker.lock().unwrap();
     let pickers_lock = pickers.lock().unwrap();
@@ -193,6 +342,8 @@ fn gtk_main(config: PathBuf, styles: PathBuf, stay_here: bool) -> glib::ExitCode
         misc::daemonize();
     }
 
+    styles::register_resources();
+
     let app = Application::builder()
         .application_id(GTK_APP_ID)
         .flags(ApplicationFlags::FLAGS_NONE | ApplicationFlags::HANDLES_COMMAND_LINE)
@@ -227,6 +378,7 @@ fn gtk_main(config: PathBuf, styles: PathBuf, stay_here: bool) -> glib::ExitCode
     glib::source::unix_signal_add(libc::SIGINT, move || {
         if let Ok(mut queue) = msg_queue_for_sigint.lock() {
             queue.push_back(AppMessage::AppClose.into());
+        }
         let _ = std::fs::remove_file(socket::get_socket_path());
         glib::ControlFlow::Break
     });
@@ -240,6 +392,56 @@ fn toggle_picker(kind: PickerKind) {
     }
 }
 
+/// Pin or unpin through the daemon, which answers with why it couldn't
+fn change_pin(message: AppMessage) {
+    match socket::request(message) {
+        Ok(reply) if reply.is_empty() => (),
+        Ok(reply) => {
+            eprintln!("{reply}");
+            std::process::exit(1);
+        }
+        Err(e) => eprintln!("Failed to send: {e}"),
+    }
+}
+
+/// Print the last `lines` launches, then poll for new ones with `follow`
+fn print_log(lines: usize, follow: bool) {
+    let records = history::read_all();
+    for record in &records[records.len().saturating_sub(lines)..] {
+        println!("{}", record.summary());
+    }
+
+    if !follow {
+        return;
+    }
+
+    let path = history::history_path();
+    let mut offset = std::fs::metadata(&path).map_or(0, |m| m.len());
+    loop {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+
+        let Ok(data) = std::fs::read(&path) else {
+            continue;
+        };
+
+        // rotated, the new file only has what came after
+        if (data.len() as u64) < offset {
+            offset = 0;
+        }
+
+        // a record still being written waits for the next round
+        let new = &data[offset as usize..];
+        let Some(end) = new.iter().rposition(|&b| b == b'\n') else {
+            continue;
+        };
+
+        for record in history::parse(&String::from_utf8_lossy(&new[..=end])) {
+            println!("{}", record.summary());
+        }
+        offset += end as u64 + 1;
+    }
+}
+
 fn main() {
     let cli = args::Cli::parse();
 
@@ -287,6 +489,28 @@ fn main() {
             }
         }
         args::Commands::Apps => toggle_picker(PickerKind::Apps),
+        args::Commands::Pin { id } => change_pin(AppMessage::PinApp(id)),
+        args::Commands::Unpin { id } => change_pin(AppMessage::UnpinApp(id)),
+        args::Commands::Ps => match socket::request(AppMessage::ListRunning) {
+            Ok(table) => print!("{table}"),
+            Err(e) => eprintln!("Failed to send: {e}"),
+        },
+        args::Commands::Log(args) if args.window => {
+            if let Err(e) = socket::send_message(AppMessage::ShowLog) {
+                eprintln!("Failed to send: {e}");
+            }
+        }
+        args::Commands::Log(args) => print_log(args.lines, args.follow),
+        args::Commands::Theme { name } => {
+            if name != "none" && !styles::THEMES.contains(&name.as_str()) {
+                eprintln!("Unknown theme {name}, available: {}, none", styles::THEMES.join(", "));
+                std::process::exit(1);
+            }
+
+            if let Err(e) = socket::send_message(AppMessage::SetTheme(name)) {
+                eprintln!("Failed to send: {e}");
+            }
+        }
         args::Commands::Reload => match socket::send_message(AppMessage::AppReload) {
             Err(e) => eprintln!("Failed to send: {e}"),
             _ => (),
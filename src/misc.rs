@@ -1,13 +1,4 @@
-use crate::gtk;
-
-use std::io::Read;
-use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::thread;
-use std::time::{Duration, Instant};
-
-use gtk::gdk;
-use gtk::prelude::{Cast, DisplayExt, ListModelExt, MonitorExt};
+use std::path::Path;
 
 pub(crate) struct CommandError {
     pub(crate) reason: String,
@@ -15,51 +6,6 @@ pub(crate) struct CommandError {
     pub(crate) stdout: Option<String>,
 }
 
-pub(crate) fn get_full_display_size() -> (i32, i32) {
-    let display = gdk::Display::default().expect("Failed to get default display");
-    let model = display.monitors();
-
-    let n_monitors = model.n_items();
-    if n_monitors == 0 {
-        panic!("No monitors found");
-    }
-
-    let mut x0 = i32::MAX;
-    let mut y0 = i32::MAX;
-    let mut x1 = i32::MIN;
-    let mut y1 = i32::MIN;
-
-    for i in 0..n_monitors {
-        let obj = model
-            .item(i)
-            .expect(&format!("Failed to get monitor at index {}", i));
-        let monitor = obj
-            .downcast::<gdk::Monitor>()
-            .expect("ListModel item is not a Monitor");
-
-        let geom = monitor.geometry();
-        x0 = x0.min(geom.x());
-        y0 = y0.min(geom.y());
-        x1 = x1.max(geom.x() + geom.width());
-        y1 = y1.max(geom.y() + geom.height());
-    }
-
-    let width = x1 - x0;
-    let height = y1 - y0;
-    (width, height)
-}
-
-pub(crate) fn apply_styles(filepath: &PathBuf) {
-    let provider = gtk::CssProvider::new();
-    provider.load_from_path(filepath);
-
-    if let Some(display) = gtk::gdk::Display::default() {
-        gtk::style_context_add_provider_for_display(&display, &provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
-    } else {
-        eprintln!("No display found for applying CSS.");
-    }
-}
-
 #[inline]
 pub(crate) fn get_local_path(name: &str) -> std::path::PathBuf {
     std::env::var_os("HOME")
@@ -69,56 +15,15 @@ pub(crate) fn get_local_path(name: &str) -> std::path::PathBuf {
         .join(name)
 }
 
-pub(crate) fn launch_detached(exec_command: &str) -> Result<(), CommandError> {
-    let mut child = Command::new("sh")
-        .arg("-c")
-        .arg(exec_command)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .stdin(Stdio::null())
-        .spawn()
-        .map_err(|e| CommandError {
-            reason: format!("Failed to spawn process: {}", e),
-            stderr: None,
-            stdout: None,
-        })?;
-
-    let start_time = Instant::now();
-    let timeout = Duration::from_secs(3);
-
-    loop {
-        match child.try_wait() {
-            Ok(Some(status)) => {
-                if status.success() {
-                    return Ok(());
-                }
-
-                let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
-                child.stdout.take().map(|mut v| v.read_to_end(&mut stdout));
-                child.stderr.take().map(|mut v| v.read_to_end(&mut stderr));
-
-                return Err(CommandError {
-                    reason: format!("Command failed with exit code: {}", status.code().unwrap_or(-1)),
-                    stderr: Some(String::from_utf8_lossy(&stderr).into_owned()),
-                    stdout: Some(String::from_utf8_lossy(&stdout).into_owned()),
-                });
-            }
-            Ok(None) => {
-                if start_time.elapsed() >= timeout {
-                    return Ok(());
-                }
-
-                thread::sleep(Duration::from_millis(10));
-            }
-            Err(e) => {
-                return Err(CommandError {
-                    reason: format!("Error waiting for process: {}", e),
-                    stderr: None,
-                    stdout: None,
-                });
-            }
-        }
-    }
+/// Path under `$XDG_STATE_HOME/gall` (`~/.local/state/gall` if unset)
+pub(crate) fn get_state_path(name: &str) -> std::path::PathBuf {
+    std::env::var_os("XDG_STATE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| std::path::PathBuf::from(h).join(".local/state")))
+        .expect("HOME env var is not set")
+        .join("gall")
+        .join(name)
 }
 
 pub(crate) fn expand_tilde<P: AsRef<Path>>(path: P) -> Option<std::path::PathBuf> {
@@ -138,6 +43,56 @@ pub(crate) fn expand_tilde<P: AsRef<Path>>(path: P) -> Option<std::path::PathBuf
         })
 }
 
+/// Split `text` like `sh` splits words: `'…'` is literal, `"…"` keeps `\"`, `\\`, `\$` and
+/// `` \` `` escapes, and `\` outside quotes escapes anything. An unquoted `~` starting a word
+/// is expanded, unterminated quotes run to the end
+pub(crate) fn shell_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    // the word so far, and whether it started with an unquoted `~`
+    let mut word: Option<(String, bool)> = None;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            words.extend(word.take().map(finish_word));
+            continue;
+        }
+
+        let (word, _) = word.get_or_insert_with(|| (String::new(), c == '~'));
+        match c {
+            '\'' => word.extend(chars.by_ref().take_while(|&c| c != '\'')),
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some(next @ ('"' | '\\' | '$' | '`')) => word.push(next),
+                            Some(next) => {
+                                word.push('\\');
+                                word.push(next);
+                            }
+                            None => word.push('\\'),
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => word.extend(chars.next()),
+            c => word.push(c),
+        }
+    }
+
+    words.extend(word.map(finish_word));
+    words
+}
+
+fn finish_word((word, tilde): (String, bool)) -> String {
+    if !tilde {
+        return word;
+    }
+    expand_tilde(&word).map_or(word, |p| p.to_string_lossy().into_owned())
+}
+
 #[inline]
 pub(crate) fn fuzzy(s: &str, pattern: &str) -> bool {
     let mut s_bytes = s.bytes();
@@ -162,6 +117,49 @@ pub(crate) fn fuzzy(s: &str, pattern: &str) -> bool {
     true
 }
 
+/// Smallest edit distance between `pattern` and any word of `s`, if it is within `max`
+pub(crate) fn typo_distance(s: &str, pattern: &str, max: usize) -> Option<usize> {
+    let pattern: Vec<char> = pattern.trim().to_lowercase().chars().collect();
+    if pattern.is_empty() {
+        return None;
+    }
+
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .filter_map(|w| {
+            let word: Vec<char> = w.to_lowercase().chars().collect();
+            if word.len().abs_diff(pattern.len()) > max {
+                return None;
+            }
+            Some(damerau_levenshtein(&word, &pattern))
+        })
+        .filter(|d| *d <= max)
+        .min()
+}
+
+// Optimal string alignment variant, adjacent transpositions count as one edit
+fn damerau_levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut prev2 = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        curr[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                curr[j] = curr[j].min(prev2[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut prev2, &mut prev);
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
 #[inline]
 pub(crate) fn daemonize() {
     unsafe {
@@ -183,3 +181,53 @@ pub fn unix_sched_yield() {
   }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: &str, b: &str) -> usize {
+        let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+        damerau_levenshtein(&a, &b)
+    }
+
+    #[test]
+    fn damerau_levenshtein_counts_edits() {
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("abc", "abc"), 0);
+        // a swap is one edit, not two
+        assert_eq!(distance("firefox", "fierfox"), 1);
+    }
+
+    #[test]
+    fn typo_distance_matches_the_closest_word() {
+        assert_eq!(typo_distance("Firefox Web Browser", "firfox", 2), Some(1));
+        assert_eq!(typo_distance("Firefox Web Browser", "BROWSRE", 2), Some(1));
+        assert_eq!(typo_distance("Firefox", "chrome", 2), None);
+        assert_eq!(typo_distance("Firefox", "  ", 2), None);
+        // too long or short to be in budget at all
+        assert_eq!(typo_distance("vi", "vivaldi", 2), None);
+    }
+
+    #[test]
+    fn shell_words_split_like_sh() {
+        assert_eq!(shell_words("  a  b\tc "), ["a", "b", "c"]);
+        assert_eq!(
+            shell_words(r#"'a b' "c \"d\" \n" e\ f"#),
+            ["a b", r#"c "d" \n"#, "e f"]
+        );
+        assert_eq!(shell_words(r"'it'\''s' x'y'z"), ["it's", "xyz"]);
+        assert_eq!(shell_words(r#""unterminated"#), ["unterminated"]);
+        assert_eq!(shell_words("'' x"), ["", "x"]);
+        assert!(shell_words("   ").is_empty());
+    }
+
+    #[test]
+    fn shell_words_expand_leading_tilde() {
+        let home = std::env::var("HOME").unwrap();
+        assert_eq!(
+            shell_words("~/a '~/b' a~"),
+            [format!("{home}/a"), "~/b".to_owned(), "a~".to_owned()]
+        );
+    }
+}
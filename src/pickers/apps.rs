@@ -1,18 +1,122 @@
 use crate::{
-    config::{AppEntry, ConfigLoad},
-    gtk::{self, gdk, glib},
+    config::{AppEntry, ConfigLoad, LaunchPolicy, Layout},
+    gtk::{self, gdk, gio, glib},
+    icons::{self, IconStyle},
+    keymap::{self, Action, Keymap},
+    launcher,
     misc,
     pickers::{self, Picker},
-    GallApp,
+    pins::Pins,
+    running::Running,
+    stats::{LaunchCount, LaunchStats},
+    terminal,
+    AppState, GallApp,
 };
 use gtk::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
+/// Model item, `rank` is set by the last query (`None` means filtered out)
+pub(crate) struct AppItem {
+    pub entry: AppEntry,
+    rank: Cell<Option<u64>>,
+    pinned: Cell<bool>,
+    running: Cell<bool>,
+}
+
+/// How `accept` starts the app
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AcceptMode {
+    Hide,
+    /// Stay open to start several apps in a row
+    KeepOpen,
+    /// In the terminal, even GUI apps, to see their output
+    Terminal,
+    /// Open its desktop file instead
+    Edit,
+}
+
+// history and log name for `edit` launches, reserved so no app shares its history
+const EDITOR_ID: &str = "gall:editor";
+
+// grid cells have no description
+struct AppRowWidgets {
+    icon: gtk::Image,
+    name: gtk::Label,
+    desc: Option<gtk::Label>,
+    badge: gtk::Label,
+    running: gtk::Label,
+}
+
+/// Index hints on the first nine visible items, launched with `modifier`+digit
+#[derive(Default)]
+struct QuickSelect {
+    modifier: Cell<Option<gdk::ModifierType>>,
+    /// First fully visible position
+    top: Cell<u32>,
+}
+
+/// Items the factories made rows for, to restyle rows without rebinding them
+type BoundItems = Rc<RefCell<Vec<glib::WeakRef<gtk::ListItem>>>>;
+
+/// Called with the position and the files or URIs dropped on a row
+type DropHandler = Rc<RefCell<Option<Box<dyn Fn(u32, Vec<String>)>>>>;
+
+/// Store -> filter -> sort -> selection, the views only render what's visible
+///
+/// Both views share the selection, only the one for `layout` is in the scroller
+#[derive(Clone)]
+pub(crate) struct AppList {
+    store: gio::ListStore,
+    filter: gtk::CustomFilter,
+    sorter: gtk::CustomSorter,
+    selection: gtk::SingleSelection,
+    scroll: gtk::ScrolledWindow,
+    listview: gtk::ListView,
+    gridview: gtk::GridView,
+    layout: Rc<Cell<Layout>>,
+    icon_style: Rc<Cell<IconStyle>>,
+    quick: Rc<QuickSelect>,
+    bound: BoundItems,
+    on_drop: DropHandler,
+}
+
+/// Right-hand pane with everything about the selected entry
+#[derive(Clone)]
+struct AppPreview {
+    pane: gtk::Box,
+    icon: gtk::Image,
+    name: gtk::Label,
+    desc: gtk::Label,
+    // (title, value) rows, hidden when there's nothing to show
+    details: Vec<(gtk::Label, gtk::Label)>,
+}
+
+/// Everything key actions and activation need, cheap to clone into handlers
+#[derive(Clone)]
+struct AppControls {
+    gapp: gtk::Application,
+    state: Arc<Mutex<AppPickerState>>,
+    search_input: gtk::Entry,
+    toggle_btn: gtk::Button,
+    list: AppList,
+    preview: AppPreview,
+    pins: Arc<Mutex<Pins>>,
+    stats: Arc<Mutex<LaunchStats>>,
+    running: Arc<Mutex<Running>>,
+    app_state: Arc<Mutex<AppState>>,
+    parent: Arc<GallApp>,
+}
+
 pub struct AppPickerState {
     name_fuzz: bool,
-    selected: u32,
-    fil_apps: u32,
-    all_apps: Vec<AppEntry>,
+    typo_tolerance: usize,
+    keymap: Keymap,
+    /// `preview` from the last config, so a reload only overrides `Alt+I` when it changed
+    preview: Option<bool>,
+    pins: Arc<Mutex<Pins>>,
+    running: Arc<Mutex<Running>>,
     callback: Arc<Option<Box<dyn Fn()>>>,
 }
 
@@ -22,16 +126,19 @@ pub struct AppPicker {
     mainbox: gtk::Box,
     search_input: gtk::Entry,
     toggle_btn: gtk::Button,
-    listbox: gtk::ListBox,
+    list: AppList,
+    preview: AppPreview,
 }
 
 impl AppPickerState {
-    fn new() -> Self {
+    fn new(pins: Arc<Mutex<Pins>>, running: Arc<Mutex<Running>>) -> Self {
         Self {
             name_fuzz: true,
-            selected: 0,
-            fil_apps: 0,
-            all_apps: Vec::new(),
+            typo_tolerance: 0,
+            keymap: Keymap::default(),
+            preview: None,
+            pins,
+            running,
             callback: Arc::new(None),
         }
     }
@@ -39,19 +146,20 @@ impl AppPickerState {
 
 impl AppPicker {
     pub fn new(parent: Arc<GallApp>) -> Self {
-        let (mainbox, search_input, toggle_btn, listbox) = pickers::create_picker_components();
-        let state = Arc::new(Mutex::new(AppPickerState::new()));
+        let components = pickers::create_picker_components();
+        let state = Arc::new(Mutex::new(AppPickerState::new(parent.pins.clone(), parent.running.clone())));
 
-        let _ = toggle_btn.set_icon_name("edit-find-symbolic");
-        let _ = toggle_btn.set_tooltip_text(Some("Search by name"));
+        let _ = components.toggle_btn.set_icon_name("edit-find-symbolic");
+        let _ = components.toggle_btn.set_tooltip_text(Some("Search by name"));
 
         Self {
             parent,
             state,
-            mainbox,
-            search_input,
-            toggle_btn,
-            listbox,
+            list: AppList::new(components.scroll, components.listview, components.gridview),
+            preview: AppPreview::new(components.preview),
+            mainbox: components.mainbox,
+            search_input: components.search_input,
+            toggle_btn: components.toggle_btn,
         }
     }
 }
@@ -60,7 +168,6 @@ impl Picker for AppPicker {
     fn load(&self, config: &ConfigLoad) -> bool {
         self.reload(config);
         app_picker_control(&self);
-        populate_app_list(&self.listbox, &self.state, "");
 
         true
     }
@@ -71,20 +178,19 @@ impl Picker for AppPicker {
         if had_to_load {
             self.parent.window.set_child(Some(&self.mainbox));
         }
-        self.listbox.select_row(self.listbox.row_at_index(0).as_ref());
-
-        let name_fuzz = {
-            let mut locked = self.state.lock().unwrap();
-            locked.selected = 0;
-            locked.name_fuzz
-        };
 
+        let name_fuzz = self.state.lock().unwrap().name_fuzz;
         if !name_fuzz {
             toggle_fuzzy_search_mode(&self.state, &self.toggle_btn);
         }
 
         self.search_input.grab_focus();
-        self.search_input.set_text(""); // calls populate_app_list if needed
+        // `changed` runs the query, but only if there was text to clear
+        if self.search_input.text().is_empty() {
+            self.list.apply_query(&self.state, "");
+        } else {
+            self.search_input.set_text("");
+        }
 
         had_to_load
     }
@@ -99,56 +205,642 @@ impl Picker for AppPicker {
     }
 
     fn reload(&self, config: &ConfigLoad) {
-        let mut state = self.state.lock().unwrap();
+        let options = &config.pickers.apps;
+        let preview_changed = {
+            let mut state = self.state.lock().unwrap();
+            state.name_fuzz = true;
+            state.typo_tolerance = config.typo_tolerance;
+            state.keymap = Keymap::new(&config.keymap, &config.keys);
+            state.preview.replace(options.preview) != Some(options.preview)
+        };
+
+        if preview_changed {
+            self.preview.pane.set_visible(options.preview);
+        }
+        self.list.set_quick_select(match options.quick_select.as_str() {
+            "none" => None,
+            modifier => keymap::parse_modifier(modifier).or_else(|| {
+                eprintln!("Invalid quick select modifier {modifier}, using Alt");
+                Some(gdk::ModifierType::ALT_MASK)
+            }),
+        });
+        self.list.set_layout(options.layout, options.columns);
+        self.list.set_icon_style(IconStyle {
+            size: options.icon_size,
+            symbolic: options.symbolic_icons,
+        });
+        self.list.set_apps(&config.apps);
+        self.refresh();
+    }
+
+    fn refresh(&self) {
+        self.list.apply_query(&self.state, self.search_input.text().as_str());
+    }
+
+    fn update_running(&self) {
+        self.list.update_running(&self.parent.running.lock().unwrap());
+    }
 
-        state.fil_apps = config.apps.len() as u32;
-        // TODO: cbwqbfq[bf[oqbq[bfqboe[bfoe]]]] use a slice
-        state.all_apps = config.apps.clone();
-        state.name_fuzz = true;
+    fn cancel(&self) {
+        AppControls::new(self).run(Action::Cancel);
     }
 }
 
-fn populate_app_list(listbox: &gtk::ListBox, state: &Arc<Mutex<AppPickerState>>, pattern: &str) {
-    while let Some(child) = listbox.first_child() {
-        listbox.remove(&child);
+impl AppList {
+    fn new(scroll: gtk::ScrolledWindow, listview: gtk::ListView, gridview: gtk::GridView) -> Self {
+        let store = gio::ListStore::new::<glib::BoxedAnyObject>();
+
+        let filter = gtk::CustomFilter::new(|obj| item_rank(obj).is_some());
+        let filtered = gtk::FilterListModel::new(Some(store.clone()), Some(filter.clone()));
+
+        let sorter = gtk::CustomSorter::new(|a, b| item_rank(a).cmp(&item_rank(b)).into());
+        let sorted = gtk::SortListModel::new(Some(filtered), Some(sorter.clone()));
+
+        let selection = gtk::SingleSelection::new(Some(sorted));
+        selection.set_autoselect(true);
+        selection.set_can_unselect(false);
+
+        let icon_style = Rc::new(Cell::new(IconStyle {
+            size: 48,
+            symbolic: false,
+        }));
+
+        let quick = Rc::new(QuickSelect::default());
+        let bound = BoundItems::default();
+        let on_drop = DropHandler::default();
+
+        let factory =
+            |layout| create_item_factory(icon_style.clone(), quick.clone(), bound.clone(), on_drop.clone(), layout);
+        listview.set_model(Some(&selection));
+        listview.set_factory(Some(&factory(Layout::List)));
+        gridview.set_model(Some(&selection));
+        gridview.set_factory(Some(&factory(Layout::Grid)));
+
+        let list = Self {
+            store,
+            filter,
+            sorter,
+            selection,
+            scroll,
+            listview,
+            gridview,
+            layout: Rc::new(Cell::new(Layout::List)),
+            icon_style,
+            quick,
+            bound,
+            on_drop,
+        };
+
+        {
+            // new theme icons
+            let list = list.clone();
+            icons::connect_invalidated(move || list.rebind());
+        }
+
+        {
+            // moved to a monitor with another scale
+            let list = list.clone();
+            list.scroll.clone().connect_scale_factor_notify(move |_| list.rebind());
+        }
+
+        {
+            // scrolled, or the content size changed
+            let adj = list.scroll.vadjustment();
+            let on_value = list.clone();
+            adj.connect_value_changed(move |_| on_value.update_badges());
+            let on_changed = list.clone();
+            adj.connect_changed(move |_| on_changed.update_badges());
+        }
+
+        list
     }
 
-    let mut locked = state.lock().unwrap();
+    fn connect_drop<F: Fn(u32, Vec<String>) + 'static>(&self, f: F) {
+        self.on_drop.replace(Some(Box::new(f)));
+    }
+
+    fn set_quick_select(&self, modifier: Option<gdk::ModifierType>) {
+        self.quick.modifier.set(modifier);
+        self.update_badges();
+    }
+
+    fn update_badges(&self) {
+        self.quick.top.set(self.first_visible());
+        self.for_each_row(|list_item, _, row| self.quick.set_badge(list_item, row));
+    }
+
+    /// Follow launches and exits, only touching rows so the selection stays put
+    fn update_running(&self, running: &Running) {
+        for obj in (0..self.store.n_items()).filter_map(|i| self.store.item(i).and_downcast::<glib::BoxedAnyObject>()) {
+            let item = obj.borrow::<AppItem>();
+            item.running.set(running.is_running(item.entry.id()));
+        }
+
+        self.for_each_row(|_, item, row| set_running(row, item.running.get()));
+    }
+
+    // rows currently bound to an item, dropping the ones GTK has disposed of
+    fn for_each_row<F: Fn(&gtk::ListItem, &AppItem, &gtk::Box)>(&self, f: F) {
+        self.bound.borrow_mut().retain(|weak| {
+            let Some(list_item) = weak.upgrade() else {
+                return false;
+            };
+            if let (Some(item), Some(row)) = (
+                list_item.item().and_downcast::<glib::BoxedAnyObject>(),
+                list_item.child().and_downcast::<gtk::Box>(),
+            ) {
+                f(&list_item, &item.borrow::<AppItem>(), &row);
+            }
+            true
+        });
+    }
+
+    /// Position for `digit` (1 to 9) counting from the first visible item
+    fn quick_select_position(&self, digit: u32) -> Option<u32> {
+        let pos = self.quick.top.get() + digit.checked_sub(1)?;
+        (digit <= 9 && pos < self.selection.n_items()).then_some(pos)
+    }
+
+    // a row cut in half at the top doesn't count
+    fn first_visible(&self) -> u32 {
+        let adj = self.scroll.vadjustment();
+        let columns = self.columns() as f64;
+        let rows = (self.selection.n_items() as f64 / columns).ceil();
+        if rows < 1.0 || adj.upper() <= 0.0 {
+            return 0;
+        }
+
+        let row_height = adj.upper() / rows;
+        ((adj.value() / row_height - 0.25).ceil().max(0.0) * columns) as u32
+    }
 
-    locked
-        .all_apps
-        .iter()
-        .filter(|e| {
-            if locked.name_fuzz {
-                misc::fuzzy(&e.name, pattern)
+    fn view(&self) -> gtk::Widget {
+        match self.layout.get() {
+            Layout::List => self.listview.clone().upcast(),
+            Layout::Grid => self.gridview.clone().upcast(),
+        }
+    }
+
+    fn set_layout(&self, layout: Layout, columns: u32) {
+        let columns = columns.max(1);
+        self.gridview.set_min_columns(columns);
+        self.gridview.set_max_columns(columns);
+
+        self.layout.set(layout);
+        let view = self.view();
+        if self.scroll.child().as_ref() != Some(&view) {
+            self.scroll.set_child(Some(&view));
+        }
+    }
+
+    /// Bind visible rows again, ranks are kept but the selection may go back to the top
+    fn rebind(&self) {
+        let n_items = self.store.n_items();
+        self.store.items_changed(0, n_items, n_items);
+    }
+
+    fn set_icon_style(&self, style: IconStyle) {
+        self.icon_style.set(style);
+        self.rebind();
+    }
+
+    fn set_apps(&self, apps: &[AppEntry]) {
+        let items: Vec<glib::BoxedAnyObject> = apps
+            .iter()
+            .map(|entry| {
+                glib::BoxedAnyObject::new(AppItem {
+                    entry: entry.clone(),
+                    rank: Cell::new(None),
+                    pinned: Cell::new(false),
+                    running: Cell::new(false),
+                })
+            })
+            .collect();
+
+        self.store.splice(0, self.store.n_items(), &items);
+    }
+
+    /// Rank every item for `pattern`, then let the filter and sorter models catch up
+    fn apply_query(&self, state: &Arc<Mutex<AppPickerState>>, pattern: &str) {
+        let (pattern, _) = split_query(pattern);
+        let locked = state.lock().unwrap();
+        let pins = locked.pins.clone();
+        let pins = pins.lock().unwrap();
+        let running = locked.running.clone();
+        let running = running.lock().unwrap();
+
+        let items: Vec<glib::BoxedAnyObject> = (0..self.store.n_items())
+            .filter_map(|i| self.store.item(i).and_downcast())
+            .collect();
+
+        let mut any_match = false;
+        for (i, obj) in items.iter().enumerate() {
+            let item = obj.borrow::<AppItem>();
+            let e = &item.entry;
+
+            let rank = if e.has_alias(pattern) {
+                // exact alias hits go first, so Enter launches them
+                Some(rank(0, 0, i))
+            } else if matches_pattern(&locked, e, pattern) {
+                match pins.position(e.id()) {
+                    Some(pos) if pattern.is_empty() => Some(rank(1, pos, i)),
+                    _ => Some(rank(2, 0, i)),
+                }
             } else {
-                misc::fuzzy(&e.gend.clone().unwrap_or("".to_owned()), pattern)
-                    || misc::fuzzy(&e.desc.clone().unwrap_or("".to_owned()), pattern)
+                None
+            };
+
+            any_match |= rank.is_some();
+            item.rank.set(rank);
+            item.pinned.set(pins.is_pinned(e.id()));
+            item.running.set(running.is_running(e.id()));
+        }
+
+        if !any_match {
+            let budget = typo_budget(&locked, pattern);
+            for (i, obj) in items.iter().enumerate() {
+                let item = obj.borrow::<AppItem>();
+                let rank = typo_distance(&locked, &item.entry, pattern, budget).map(|d| rank(3, d, i));
+                item.rank.set(rank);
             }
-        })
-        .for_each(|e| {
-            let app_row = create_app_row(e);
-            listbox.append(&app_row);
+        }
+
+        drop(running);
+        drop(pins);
+        drop(locked);
+
+        // rows that keep their position aren't rebound
+        self.for_each_row(|_, item, row| {
+            set_pinned(row, item.pinned.get());
+            set_running(row, item.running.get());
         });
 
-    locked.fil_apps = listbox.observe_children().n_items();
+        self.filter.changed(gtk::FilterChange::Different);
+        self.sorter.changed(gtk::SorterChange::Different);
+        self.select(0);
+        self.update_badges();
+    }
+
+    fn select(&self, pos: u32) {
+        if pos >= self.selection.n_items() {
+            return;
+        }
+
+        self.selection.set_selected(pos);
+        let _ = self.view().activate_action("list.scroll-to-item", Some(&pos.to_variant()));
+    }
+
+    /// Move the selection by `offset`, wrapping around the ends or stopping at them
+    fn select_offset(&self, offset: i32, wrap: bool) {
+        let n_items = self.selection.n_items() as i64;
+        if n_items == 0 {
+            return;
+        }
+
+        let next = match self.selection.selected() {
+            gtk::INVALID_LIST_POSITION => 0,
+            c if wrap => (c as i64 + offset as i64).rem_euclid(n_items),
+            c => (c as i64 + offset as i64).clamp(0, n_items - 1),
+        };
+
+        self.select(next as u32);
+    }
+
+    fn is_grid(&self) -> bool {
+        self.layout.get() == Layout::Grid
+    }
+
+    /// Items per visual row, always 1 for the list
+    fn columns(&self) -> i32 {
+        if self.is_grid() {
+            self.gridview.max_columns() as i32
+        } else {
+            1
+        }
+    }
+
+    /// Items that fit in the scroller, rows are assumed to be the same height
+    fn page_size(&self) -> i32 {
+        let adj = self.scroll.vadjustment();
+        let rows = (self.selection.n_items() as f64 / self.columns() as f64).ceil();
+        if rows < 1.0 || adj.upper() <= 0.0 {
+            return self.columns();
+        }
+
+        let row_height = adj.upper() / rows;
+        ((adj.page_size() / row_height).floor() as i32).max(1) * self.columns()
+    }
 
-    if locked.selected > locked.fil_apps {
-        locked.selected = 0
+    fn entry_at(&self, pos: u32) -> Option<AppEntry> {
+        self.selection
+            .item(pos)
+            .and_downcast::<glib::BoxedAnyObject>()
+            .map(|obj| obj.borrow::<AppItem>().entry.clone())
     }
 
-    listbox.select_row(listbox.row_at_index(locked.selected as i32).as_ref());
+    fn selected_entry(&self) -> Option<AppEntry> {
+        self.entry_at(self.selection.selected())
+    }
+}
+
+impl AppPreview {
+    const DETAILS: [&'static str; 5] = ["Exec", "File", "Categories", "Keywords", "Launched"];
+
+    fn new(pane: gtk::Box) -> Self {
+        let icon = gtk::Image::builder()
+            .name("preview-image")
+            .halign(gtk::Align::Center)
+            .build();
+
+        let name = gtk::Label::builder()
+            .name("preview-name")
+            .wrap(true)
+            .justify(gtk::Justification::Center)
+            .build();
+
+        let desc = gtk::Label::builder()
+            .name("preview-desc")
+            .wrap(true)
+            .xalign(0.0)
+            .build();
+
+        let grid = gtk::Grid::builder()
+            .name("preview-details")
+            .row_spacing(4)
+            .column_spacing(8)
+            .build();
+
+        let details: Vec<(gtk::Label, gtk::Label)> = Self::DETAILS
+            .iter()
+            .enumerate()
+            .map(|(i, title)| {
+                let title = gtk::Label::builder()
+                    .label(*title)
+                    .xalign(0.0)
+                    .yalign(0.0)
+                    .css_classes(["dim-label"])
+                    .build();
+                let value = gtk::Label::builder()
+                    .wrap(true)
+                    .wrap_mode(gtk::pango::WrapMode::WordChar)
+                    .selectable(true)
+                    .xalign(0.0)
+                    .hexpand(true)
+                    .build();
+                grid.attach(&title, 0, i as i32, 1, 1);
+                grid.attach(&value, 1, i as i32, 1, 1);
+                (title, value)
+            })
+            .collect();
+
+        let scroll = gtk::ScrolledWindow::builder()
+            .name("preview-scroll")
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vexpand(true)
+            .build();
+
+        let inner = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(8)
+            .build();
+        inner.append(&icon);
+        inner.append(&name);
+        inner.append(&desc);
+        inner.append(&grid);
+        scroll.set_child(Some(&inner));
+        pane.append(&scroll);
+
+        Self {
+            pane,
+            icon,
+            name,
+            desc,
+            details,
+        }
+    }
+
+    fn update(&self, app: Option<&AppEntry>, launches: Option<LaunchCount>, icon_style: IconStyle) {
+        let Some(app) = app else {
+            self.icon.clear();
+            self.name.set_text("");
+            self.desc.set_visible(false);
+            self.details.iter().for_each(|(t, v)| {
+                t.set_visible(false);
+                v.set_visible(false);
+            });
+            return;
+        };
+
+        match &app.icon {
+            Some(icon_str) => {
+                let style = IconStyle {
+                    size: icon_style.size * 2,
+                    ..icon_style
+                };
+                icons::set_icon(&self.icon, icon_str, style);
+            }
+            None => self.icon.clear(),
+        }
+
+        let name_markup = match &app.gend {
+            Some(g) if g != &app.name => format!(
+                "<span size=\"large\"><b>{}</b></span>\n<i>{}</i>",
+                glib::markup_escape_text(&app.name),
+                glib::markup_escape_text(g),
+            ),
+            _ => format!("<span size=\"large\"><b>{}</b></span>", glib::markup_escape_text(&app.name)),
+        };
+        self.name.set_markup(&name_markup);
+
+        self.desc.set_text(app.desc.as_deref().unwrap_or(""));
+        self.desc.set_visible(app.desc.is_some());
+
+        let launched = launches.map(|l| {
+            let last = glib::DateTime::from_unix_local(l.last)
+                .and_then(|d| d.format("%Y-%m-%d %H:%M"))
+                .map_or(String::new(), |d| format!(", last {d}"));
+            format!("{} times{}", l.count, last)
+        });
+
+        let values = [
+            Some(app.exec.clone()),
+            app.path.as_ref().map(|p| p.display().to_string()),
+            Some(app.categories.join(", ")).filter(|v| !v.is_empty()),
+            Some(app.keywords.join(", ")).filter(|v| !v.is_empty()),
+            Some(launched.unwrap_or("Never".to_owned())),
+        ];
+
+        for ((title, label), value) in self.details.iter().zip(values) {
+            title.set_visible(value.is_some());
+            label.set_visible(value.is_some());
+            label.set_text(value.as_deref().unwrap_or(""));
+        }
+    }
+}
+
+impl QuickSelect {
+    fn set_badge(&self, list_item: &gtk::ListItem, row: &gtk::Box) {
+        let Some(widgets) = (unsafe { row.data::<AppRowWidgets>("widgets") }) else {
+            return;
+        };
+        let badge = &unsafe { widgets.as_ref() }.badge;
+
+        // kept in place while empty so rows don't change size
+        let index = self.modifier.get().and_then(|_| {
+            let index = list_item.position().checked_sub(self.top.get())?;
+            (index < 9).then_some(index + 1)
+        });
+        match index {
+            Some(index) => {
+                badge.set_text(&index.to_string());
+                badge.add_css_class("quick-select-badge");
+            }
+            None => {
+                badge.set_text("");
+                badge.remove_css_class("quick-select-badge");
+            }
+        }
+    }
+}
+
+fn item_rank(obj: &glib::Object) -> Option<u64> {
+    obj.downcast_ref::<glib::BoxedAnyObject>()
+        .and_then(|obj| obj.borrow::<AppItem>().rank.get())
+}
+
+// tier first, then a tier specific key, then the original order
+fn rank(tier: u64, key: usize, index: usize) -> u64 {
+    (tier << 48) | ((key as u64 & 0xffffff) << 24) | (index as u64 & 0xffffff)
+}
+
+fn matches_pattern(state: &AppPickerState, e: &AppEntry, pattern: &str) -> bool {
+    if state.name_fuzz {
+        misc::fuzzy(&e.name, pattern)
+    } else {
+        misc::fuzzy(&e.gend.clone().unwrap_or("".to_owned()), pattern)
+            || misc::fuzzy(&e.desc.clone().unwrap_or("".to_owned()), pattern)
+    }
+}
+
+// Edit distance fallback, short queries get fewer (or no) edits so they don't match everything
+fn typo_budget(state: &AppPickerState, pattern: &str) -> usize {
+    match pattern.trim().chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+    .min(state.typo_tolerance)
+}
+
+fn typo_distance(state: &AppPickerState, e: &AppEntry, pattern: &str, budget: usize) -> Option<usize> {
+    if budget == 0 {
+        return None;
+    }
+
+    if state.name_fuzz {
+        misc::typo_distance(&e.name, pattern, budget)
+    } else {
+        let gend = misc::typo_distance(e.gend.as_deref().unwrap_or(""), pattern, budget);
+        let desc = misc::typo_distance(e.desc.as_deref().unwrap_or(""), pattern, budget);
+        gend.into_iter().chain(desc).min()
+    }
+}
+
+/// `"firefox -- https://example.com"` searches for `firefox` and passes the words after ` -- `
+/// to the launch, split and quoted like `sh` does (`code -- ~/'My Project'`)
+fn split_query(text: &str) -> (&str, Vec<String>) {
+    let (query, args) = match text.split_once(" -- ") {
+        Some((query, args)) => (query, args),
+        None => (text.strip_suffix(" --").unwrap_or(text), ""),
+    };
+
+    (query.trim_end(), misc::shell_words(args))
+}
+
+fn create_item_factory(
+    icon_style: Rc<Cell<IconStyle>>,
+    quick: Rc<QuickSelect>,
+    bound: BoundItems,
+    on_drop: DropHandler,
+    layout: Layout,
+) -> gtk::SignalListItemFactory {
+    let factory = gtk::SignalListItemFactory::new();
+
+    factory.connect_setup(move |_, obj| {
+        let Some(list_item) = obj.downcast_ref::<gtk::ListItem>() else {
+            return;
+        };
+        let child = match layout {
+            Layout::List => create_app_row(),
+            Layout::Grid => create_app_cell(),
+        };
+        child.add_controller(create_drop_target(list_item, on_drop.clone()));
+        list_item.set_child(Some(&child));
+        bound.borrow_mut().push(list_item.downgrade());
+    });
+
+    factory.connect_bind(move |_, obj| {
+        let Some(list_item) = obj.downcast_ref::<gtk::ListItem>() else {
+            return;
+        };
+        let (Some(item), Some(row)) = (
+            list_item.item().and_downcast::<glib::BoxedAnyObject>(),
+            list_item.child().and_downcast::<gtk::Box>(),
+        ) else {
+            return;
+        };
+
+        let item = item.borrow::<AppItem>();
+        bind_app_row(&row, &item.entry, item.pinned.get(), icon_style.get());
+        set_running(&row, item.running.get());
+        quick.set_badge(list_item, &row);
+    });
 
-    listbox.show();
+    factory
 }
 
-fn create_app_row(app: &AppEntry) -> gtk::ListBoxRow {
-    let row = gtk::ListBoxRow::new();
-    row.set_widget_name("app-row");
+// files become paths, anything else stays a URI
+fn create_drop_target(list_item: &gtk::ListItem, on_drop: DropHandler) -> gtk::DropTarget {
+    let target = gtk::DropTarget::new(glib::Type::INVALID, gdk::DragAction::COPY);
+    target.set_types(&[gdk::FileList::static_type(), glib::Type::STRING]);
+
+    let list_item = list_item.downgrade();
+    target.connect_drop(move |_, value, _, _| {
+        let dropped: Vec<String> = if let Ok(files) = value.get::<gdk::FileList>() {
+            files
+                .files()
+                .iter()
+                .map(|f| f.path().map_or_else(|| f.uri().to_string(), |p| p.to_string_lossy().into_owned()))
+                .collect()
+        } else if let Ok(text) = value.get::<String>() {
+            text.lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(str::to_owned)
+                .collect()
+        } else {
+            Vec::new()
+        };
 
-    unsafe { row.set_data("exec", app.exec.clone()) };
+        let Some(list_item) = list_item.upgrade() else {
+            return false;
+        };
+        let pos = list_item.position();
+        match on_drop.borrow().as_ref() {
+            Some(on_drop) if !dropped.is_empty() && pos != gtk::INVALID_LIST_POSITION => {
+                on_drop(pos, dropped);
+                true
+            }
+            _ => false,
+        }
+    });
 
+    target
+}
+
+fn create_app_row() -> gtk::Box {
     let hbox = gtk::Box::builder()
+        .name("app-row")
         .orientation(gtk::Orientation::Horizontal)
         .spacing(2)
         .margin_start(10)
@@ -157,17 +849,167 @@ fn create_app_row(app: &AppEntry) -> gtk::ListBoxRow {
         .margin_bottom(5)
         .build();
 
-    if let Some(icon_str) = &app.icon {
-        if let Some(icon) = crate::blocks::create_icon_widget(icon_str, 48) {
-            hbox.append(&icon);
-        }
-    }
+    let icon = gtk::Image::builder()
+        .overflow(gtk::Overflow::Hidden)
+        .name("app-row-image")
+        .build();
+    hbox.append(&icon);
 
     let text_box = gtk::Box::builder()
         .orientation(gtk::Orientation::Vertical)
         .spacing(2)
         .build();
 
+    let name = gtk::Label::new(None);
+    name.set_halign(gtk::Align::Start);
+    text_box.append(&name);
+
+    let desc = gtk::Label::new(None);
+    desc.set_halign(gtk::Align::Start);
+    desc.add_css_class("dim-label");
+    text_box.append(&desc);
+
+    hbox.append(&text_box);
+
+    let running = gtk::Label::builder()
+        .name("app-row-running")
+        .label("●")
+        .tooltip_text("Running")
+        .hexpand(true)
+        .halign(gtk::Align::End)
+        .valign(gtk::Align::Center)
+        .visible(false)
+        .build();
+    hbox.append(&running);
+
+    let badge = gtk::Label::builder()
+        .name("app-row-badge")
+        .halign(gtk::Align::End)
+        .valign(gtk::Align::Center)
+        .build();
+    hbox.append(&badge);
+
+    let desc = Some(desc);
+    unsafe {
+        hbox.set_data(
+            "widgets",
+            AppRowWidgets {
+                icon,
+                name,
+                desc,
+                badge,
+                running,
+            },
+        )
+    };
+
+    hbox
+}
+
+fn create_app_cell() -> gtk::Box {
+    let vbox = gtk::Box::builder()
+        .name("app-cell")
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(4)
+        .margin_start(5)
+        .margin_end(5)
+        .margin_top(5)
+        .margin_bottom(5)
+        .build();
+
+    // running dot on the left, quick select number on the right
+    let top = gtk::Box::builder().orientation(gtk::Orientation::Horizontal).build();
+
+    let running = gtk::Label::builder()
+        .name("app-cell-running")
+        .label("●")
+        .tooltip_text("Running")
+        .halign(gtk::Align::Start)
+        .visible(false)
+        .build();
+    top.append(&running);
+
+    let badge = gtk::Label::builder()
+        .name("app-cell-badge")
+        .hexpand(true)
+        .halign(gtk::Align::End)
+        .build();
+    top.append(&badge);
+    vbox.append(&top);
+
+    let icon = gtk::Image::builder()
+        .overflow(gtk::Overflow::Hidden)
+        .name("app-cell-image")
+        .halign(gtk::Align::Center)
+        .build();
+    vbox.append(&icon);
+
+    let name = gtk::Label::builder()
+        .name("app-cell-name")
+        .justify(gtk::Justification::Center)
+        .ellipsize(gtk::pango::EllipsizeMode::End)
+        .max_width_chars(12)
+        .build();
+    vbox.append(&name);
+
+    unsafe {
+        vbox.set_data(
+            "widgets",
+            AppRowWidgets {
+                icon,
+                name,
+                desc: None,
+                badge,
+                running,
+            },
+        )
+    };
+
+    vbox
+}
+
+fn set_pinned(row: &gtk::Box, pinned: bool) {
+    if pinned {
+        row.add_css_class("pinned");
+    } else {
+        row.remove_css_class("pinned");
+    }
+}
+
+fn set_running(row: &gtk::Box, running: bool) {
+    let Some(widgets) = (unsafe { row.data::<AppRowWidgets>("widgets") }) else {
+        return;
+    };
+
+    unsafe { widgets.as_ref() }.running.set_visible(running);
+    if running {
+        row.add_css_class("running");
+    } else {
+        row.remove_css_class("running");
+    }
+}
+
+fn bind_app_row(row: &gtk::Box, app: &AppEntry, pinned: bool, icon_style: IconStyle) {
+    let Some(widgets) = (unsafe { row.data::<AppRowWidgets>("widgets") }) else {
+        return;
+    };
+    let widgets = unsafe { widgets.as_ref() };
+
+    set_pinned(row, pinned);
+
+    match &app.icon {
+        Some(icon_str) => {
+            widgets.icon.set_visible(true);
+            icons::set_icon(&widgets.icon, icon_str, icon_style);
+        }
+        None => widgets.icon.set_visible(false),
+    }
+
+    let Some(desc_label) = &widgets.desc else {
+        widgets.name.set_text(&app.name);
+        return;
+    };
+
     let name_markup = match &app.gend {
         Some(g) if g != &app.name => format!(
             "<b>{}</b> - <i>{}</i>",
@@ -176,28 +1018,21 @@ fn create_app_row(app: &AppEntry) -> gtk::ListBoxRow {
         ),
         _ => format!("<b>{}</b>", glib::markup_escape_text(&app.name)),
     };
+    widgets.name.set_markup(&name_markup);
 
-    let name_label = gtk::Label::new(None);
-    name_label.set_markup(&name_markup);
-    name_label.set_halign(gtk::Align::Start);
-    text_box.append(&name_label);
-
-    if let Some(desc) = &app.desc {
-        let short_desc = if desc.len() > 60 {
-            format!("{}...", &desc[..60])
-        } else {
-            desc.clone()
-        };
-        let desc_label = gtk::Label::new(Some(&short_desc));
-        desc_label.set_halign(gtk::Align::Start);
-        desc_label.style_context().add_class("dim-label");
-        text_box.append(&desc_label);
+    match &app.desc {
+        Some(desc) => {
+            // by chars, a byte index can land inside one
+            let short_desc = if desc.chars().count() > 60 {
+                format!("{}...", desc.chars().take(60).collect::<String>())
+            } else {
+                desc.clone()
+            };
+            desc_label.set_text(&short_desc);
+            desc_label.set_visible(true);
+        }
+        None => desc_label.set_visible(false),
     }
-
-    hbox.append(&text_box);
-    row.set_child(Some(&hbox));
-
-    row
 }
 
 fn toggle_fuzzy_search_mode(state: &Arc<Mutex<AppPickerState>>, toggle_btn: &gtk::Button) {
@@ -213,133 +1048,299 @@ fn toggle_fuzzy_search_mode(state: &Arc<Mutex<AppPickerState>>, toggle_btn: &gtk
     }
 }
 
-fn launch_command_helper(exec: String, app: &gtk::Application) -> () {
-    let cmde = std::thread::spawn(move || misc::launch_detached(&exec));
-    let app = app.clone();
+fn update_preview(preview: &AppPreview, list: &AppList, stats: &Arc<Mutex<LaunchStats>>) {
+    if !preview.pane.is_visible() {
+        return;
+    }
+
+    let app = list.selected_entry();
+    let launches = app.as_ref().and_then(|a| stats.lock().unwrap().get(a.id()));
+    preview.update(app.as_ref(), launches, list.icon_style.get());
+}
 
-    // just to ensure it's used once
-    let mut cmde = Some(cmde);
+impl AppControls {
+    fn new(picker: &AppPicker) -> Self {
+        Self {
+            gapp: picker.parent.app.clone(),
+            state: picker.state.clone(),
+            search_input: picker.search_input.clone(),
+            toggle_btn: picker.toggle_btn.clone(),
+            list: picker.list.clone(),
+            preview: picker.preview.clone(),
+            pins: picker.parent.pins.clone(),
+            stats: picker.parent.stats.clone(),
+            running: picker.parent.running.clone(),
+            app_state: picker.parent.state.clone(),
+            parent: picker.parent.clone(),
+        }
+    }
 
-    glib::timeout_add_local(std::time::Duration::from_millis(250), move || {
-        if let Some(ref handle) = cmde {
-            if !handle.is_finished() {
-                return glib::ControlFlow::Continue;
+    /// Run `action`, false if it doesn't apply here so the key goes on to the entry
+    fn run(&self, action: Action) -> bool {
+        let list = &self.list;
+        let grid = list.is_grid();
+
+        match action {
+            Action::SelectNext if grid => list.select_offset(list.columns(), false),
+            Action::SelectPrev if grid => list.select_offset(-list.columns(), false),
+            Action::SelectNext => list.select_offset(1, true),
+            Action::SelectPrev => list.select_offset(-1, true),
+            // the entry keeps them until its cursor can't go further
+            Action::SelectRight if grid && self.cursor_at_end() => list.select_offset(1, true),
+            Action::SelectLeft if grid && self.search_input.position() == 0 => list.select_offset(-1, true),
+            Action::SelectLeft | Action::SelectRight => return false,
+            Action::PageDown => list.select_offset(list.page_size(), false),
+            Action::PageUp => list.select_offset(-list.page_size(), false),
+            Action::First => list.select(0),
+            Action::Last => list.select(list.selection.n_items().saturating_sub(1)),
+            Action::Accept => self.accept(list.selection.selected(), AcceptMode::Hide),
+            Action::AcceptKeepOpen => self.accept(list.selection.selected(), AcceptMode::KeepOpen),
+            Action::AcceptTerminal => self.accept(list.selection.selected(), AcceptMode::Terminal),
+            Action::EditEntry => self.accept(list.selection.selected(), AcceptMode::Edit),
+            Action::Cancel => {
+                self.search_input.set_text("");
+                list.select(0);
+                self.done();
+            }
+            Action::ToggleMode => {
+                toggle_fuzzy_search_mode(&self.state, &self.toggle_btn);
+                list.apply_query(&self.state, self.search_input.text().as_str());
+            }
+            Action::Clear => {
+                if self.search_input.text().is_empty() {
+                    toggle_fuzzy_search_mode(&self.state, &self.toggle_btn);
+                }
+                self.search_input.set_text("");
+            }
+            Action::DeleteWord => self.delete_word(),
+            Action::TogglePin => {
+                if let Some(app) = list.selected_entry() {
+                    let toggled = self.pins.lock().unwrap().toggle(app.id());
+                    if toggled.is_none() {
+                        let reason = format!(
+                            "{} is pinned in the config file, remove it there",
+                            glib::markup_escape_text(&app.name)
+                        );
+                        self.show_error(reason);
+                    }
+                    list.apply_query(&self.state, self.search_input.text().as_str());
+                }
+            }
+            Action::Terminate | Action::Kill => {
+                let signal = if action == Action::Kill { libc::SIGKILL } else { libc::SIGTERM };
+                if let Some(app) = list.selected_entry() {
+                    self.running.lock().unwrap().signal(app.id(), signal);
+                }
+            }
+            Action::ShowLog => {
+                self.done();
+                self.parent.show_log();
+            }
+            Action::TogglePreview => {
+                self.preview.pane.set_visible(!self.preview.pane.is_visible());
+                update_preview(&self.preview, list, &self.stats);
             }
-        } else {
-            return glib::ControlFlow::Break;
         }
 
-        let handle = cmde.take();
+        true
+    }
 
-        if let Some(handle) = handle {
-            let jhres = handle.join();
+    /// Position for modifier+digit, launched without moving the selection
+    ///
+    /// The digit comes from the key, not the symbol it made: Shift+1 is `exclam`, and AZERTY
+    /// layouts need Shift for digits at all
+    fn quick_select(&self, event: &gdk::KeyEvent) -> Option<u32> {
+        let modifier = self.list.quick.modifier.get()?;
+        let state = event.modifier_state();
+        if state & keymap::mods_mask() != modifier {
+            return None;
+        }
 
-            if jhres.is_err() {
-                return glib::ControlFlow::Break;
-            }
+        let display = event.display()?;
+        let shift = gdk::ModifierType::SHIFT_MASK;
+        let translate = |state| display.translate_key(event.keycode(), state, event.layout() as i32);
 
-            let jhres = jhres.unwrap();
+        let digit = [translate(state - shift), translate(state | shift)]
+            .into_iter()
+            .flatten()
+            .find_map(|(key, ..)| key.to_unicode()?.to_digit(10))?;
+        self.list.quick_select_position(digit)
+    }
 
-            if let Err(error) = jhres {
-                crate::blocks::create_error_window(&app, error);
-                return glib::ControlFlow::Break;
-            }
+    /// Launch the app at `pos` with the arguments after ` -- ` in the query
+    fn accept(&self, pos: u32, mode: AcceptMode) {
+        let (_, args) = split_query(self.search_input.text().as_str());
+        self.accept_with(pos, mode, &args);
+    }
+
+    fn accept_with(&self, pos: u32, mode: AcceptMode, args: &[String]) {
+        let Some(app) = self.list.entry_at(pos) else {
+            return;
+        };
+
+        // startup tokens need the window still focused, so everything is ready before hiding it
+        let launches = match mode {
+            AcceptMode::Edit => Some((EDITOR_ID, vec![self.edit_command(&app)])),
+            AcceptMode::Terminal => self.app_commands(&app, true, args).map(|c| (app.id(), c)),
+            AcceptMode::Hide | AcceptMode::KeepOpen => self.app_commands(&app, app.terminal, args).map(|c| (app.id(), c)),
+        };
+
+        if mode != AcceptMode::KeepOpen {
+            self.done();
         }
+        let Some((id, launches)) = launches else {
+            return;
+        };
 
-        glib::ControlFlow::Continue
-    });
+        if mode != AcceptMode::Edit {
+            self.stats.lock().unwrap().record(id);
+        }
+        for (exec, policy) in launches {
+            let (list, running) = (self.list.clone(), self.running.clone());
+            launcher::launch(&self.gapp, &self.running, id, &exec, &policy, move || {
+                list.update_running(&running.lock().unwrap());
+            });
+        }
+    }
+
+    // what Return with the held modifiers would do, so Shift+click is Shift+Return
+    fn click_mode(&self) -> AcceptMode {
+        let modifiers = gdk::Display::default()
+            .and_then(|display| display.default_seat())
+            .and_then(|seat| seat.keyboard())
+            .map(|keyboard| keyboard.modifier_state())
+            .unwrap_or_else(gdk::ModifierType::empty);
+
+        match self.state.lock().unwrap().keymap.lookup(gdk::Key::Return, modifiers) {
+            Some(Action::AcceptKeepOpen) => AcceptMode::KeepOpen,
+            Some(Action::AcceptTerminal) => AcceptMode::Terminal,
+            Some(Action::EditEntry) => AcceptMode::Edit,
+            _ => AcceptMode::Hide,
+        }
+    }
+
+    /// What to run for `app` with `args`, each with its startup token. `None` if it needs a
+    /// terminal and there's none, after saying so
+    fn app_commands(&self, app: &AppEntry, in_terminal: bool, args: &[String]) -> Option<Vec<(String, LaunchPolicy)>> {
+        let mut commands = app.commands(args);
+        if in_terminal {
+            let template = self.app_state.lock().unwrap().config.terminal_command.clone();
+            let Some(template) = template else {
+                let name = glib::markup_escape_text(&app.name);
+                self.show_error(format!("No terminal to run {name} in, set `terminal` in the config"));
+                return None;
+            };
+            commands = commands.iter().map(|command| terminal::command(&template, command)).collect();
+        }
+
+        let launches = commands
+            .into_iter()
+            .map(|exec| {
+                let mut policy = app.policy.clone();
+                policy.startup_id = launcher::startup_id(app);
+                (exec, policy)
+            })
+            .collect();
+        Some(launches)
+    }
+
+    /// The desktop file in `$EDITOR` (run in the terminal), config apps open the config.
+    /// Without `$EDITOR` or a terminal it's left to `xdg-open`
+    fn edit_command(&self, app: &AppEntry) -> (String, LaunchPolicy) {
+        let (file, template, mut policy) = {
+            let app_state = self.app_state.lock().unwrap();
+            let file = app.path.clone().unwrap_or_else(|| app_state.config_path.clone());
+            (file, app_state.config.terminal_command.clone(), app_state.config.global_policy())
+        };
+
+        let file = terminal::quote(&file.to_string_lossy());
+        let editor = std::env::var("EDITOR").ok().filter(|e| !e.trim().is_empty());
+        let exec = match (editor, template) {
+            (Some(editor), Some(template)) => terminal::command(&template, &format!("{editor} {file}")),
+            _ => format!("xdg-open {file}"),
+        };
+
+        policy.startup_id = launcher::command_startup_id(&exec, EDITOR_ID);
+        (exec, policy)
+    }
+
+    // `reason` is markup
+    fn show_error(&self, reason: String) {
+        let error = misc::CommandError { reason, stdout: None, stderr: None };
+        crate::blocks::create_error_window(&self.gapp, error);
+    }
+
+    fn done(&self) {
+        let locked = self.state.lock().unwrap();
+        if let Some(ref callback) = *locked.callback {
+            callback();
+        }
+    }
+
+    fn cursor_at_end(&self) -> bool {
+        self.search_input.position() as usize >= self.search_input.text().chars().count()
+    }
+
+    // backwards to the start of the word, spaces before the cursor go with it
+    fn delete_word(&self) {
+        let text: Vec<char> = self.search_input.text().chars().collect();
+        let end = (self.search_input.position().max(0) as usize).min(text.len());
+
+        let mut start = end;
+        while start > 0 && text[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !text[start - 1].is_whitespace() {
+            start -= 1;
+        }
+
+        self.search_input.delete_text(start as i32, end as i32);
+    }
 }
 
 fn app_picker_control(picker: &AppPicker) {
+    let controls = AppControls::new(picker);
+
+    {
+        let preview = picker.preview.clone();
+        let list = picker.list.clone();
+        let stats = picker.parent.stats.clone();
+
+        picker.list.selection.connect_selected_item_notify(move |_| {
+            update_preview(&preview, &list, &stats);
+        });
+    }
+
     {
-        let listbox = picker.listbox.clone();
+        let list = picker.list.clone();
         let state = picker.state.clone();
 
         picker.search_input.connect_changed(move |entry| {
             let text = entry.text();
-            populate_app_list(&listbox, &state, text.as_str());
+            list.apply_query(&state, text.as_str());
         });
     }
 
     {
+        // capture, so bindings win over the entry's own (Ctrl+Home, ...)
         let key_controller = gtk::EventControllerKey::new();
+        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+
+        let controls = controls.clone();
+        key_controller.connect_key_pressed(move |controller, keyval, _keycode, state| {
+            // `[keys]` win over quick select on the same chord
+            let action = controls.state.lock().unwrap().keymap.lookup(keyval, state);
+            if action.is_some_and(|action| controls.run(action)) {
+                return glib::Propagation::Stop;
+            }
 
-        // Clone references for the closure
-        let search_input = picker.search_input.clone();
-        let listbox = picker.listbox.clone();
-        let toggle_btn = picker.toggle_btn.clone();
-        let picker_state = picker.state.clone();
-
-        key_controller.connect_key_pressed(move |_controller, keyval, _keycode, state| {
-            match keyval {
-                // Ctrl+Esc: Clear input or switch to description search
-                gdk::Key::Escape if state.contains(gdk::ModifierType::CONTROL_MASK) => {
-                    if search_input.text().is_empty() {
-                        {
-                            let pstate = picker_state.clone();
-                            toggle_fuzzy_search_mode(&pstate, &toggle_btn);
-                        }
-                    }
-                    search_input.set_text("");
-                    glib::Propagation::Stop
-                }
-
-                // Escape + Ctrl+Return (search_input takes Return)
-                gdk::Key::Return | gdk::Key::Escape => {
-                    search_input.set_text("");
-                    listbox.select_row(listbox.row_at_index(0).as_ref());
-                    {
-                        let mut locked = picker_state.lock().unwrap();
-                        locked.selected = 0;
-                        if let Some(ref callback) = *locked.callback {
-                            callback();
-                        }
-                    }
-                    glib::Propagation::Stop
-                }
-
-                // Up arrow: Move up in list
-                gdk::Key::Up => {
-                    let mut locked = picker_state.lock().unwrap();
-                    if locked.fil_apps > 0 {
-                        if locked.selected > 0 {
-                            locked.selected -= 1;
-                        } else {
-                            locked.selected = locked.fil_apps - 1;
-                        }
-
-                        let row = listbox.row_at_index(locked.selected as i32);
-                        listbox.select_row(row.as_ref());
-                        row.map(|r| {
-                            r.grab_focus();
-                            search_input.grab_focus();
-                        });
-                    }
-                    glib::Propagation::Stop
-                }
-
-                // Down arrow: Move down in list
-                gdk::Key::Down => {
-                    let mut locked = picker_state.lock().unwrap();
-                    if locked.fil_apps > 0 {
-                        let max_index = locked.fil_apps - 1;
-                        if locked.selected < max_index {
-                            locked.selected += 1;
-                        } else {
-                            locked.selected = 0;
-                        }
-
-                        let row = listbox.row_at_index(locked.selected as i32);
-                        listbox.select_row(row.as_ref());
-                        row.map(|r| {
-                            r.grab_focus();
-                            search_input.grab_focus();
-                        });
-                    }
+            let event = controller.current_event().and_then(|e| e.downcast::<gdk::KeyEvent>().ok());
+            match event.and_then(|event| controls.quick_select(&event)) {
+                Some(pos) => {
+                    controls.accept(pos, AcceptMode::Hide);
                     glib::Propagation::Stop
                 }
-
-                _ => glib::Propagation::Proceed,
+                None => glib::Propagation::Proceed,
             }
         });
 
@@ -347,48 +1348,22 @@ fn app_picker_control(picker: &AppPicker) {
     }
 
     {
-        let state = picker.state.clone();
-        let listbox = picker.listbox.clone();
-        let gapp = picker.parent.app.clone();
-
+        // only reached if Return isn't bound
+        let controls = controls.clone();
         picker.search_input.connect_activate(move |_| {
-            let row: gtk::ListBoxRow;
-            {
-                let locked = state.lock().unwrap();
-                row = listbox
-                    .row_at_index(locked.selected as i32)
-                    .expect("Invalid row");
-
-                if let Some(ref callback) = *locked.callback {
-                    callback();
-                }
-            }
-
-            let exec = unsafe { row.data::<String>("exec").map(|v| v.as_ref().clone()) };
-            if let Some(exec) = exec {
-                launch_command_helper(exec, &gapp);
-            }
+            controls.accept(controls.list.selection.selected(), controls.click_mode());
         });
     }
 
     {
-        let listbox = picker.listbox.clone();
-        let gapp = picker.parent.app.clone();
-        let state = picker.state.clone();
-
-        listbox.connect_row_activated(move |_, row| {
-            {
-                let locked = state.lock().unwrap();
-                if let Some(ref callback) = *locked.callback {
-                    callback();
-                }
-            }
+        let controls = controls.clone();
+        picker.list.connect_drop(move |pos, dropped| controls.accept_with(pos, AcceptMode::Hide, &dropped));
+    }
 
-            let exec = unsafe { row.data::<String>("exec").map(|v| v.as_ref().clone()) };
-            if let Some(exec) = exec {
-                launch_command_helper(exec, &gapp);
-            }
-        });
+    {
+        let activate = controls.clone();
+        picker.list.listview.connect_activate(move |_, pos| activate.accept(pos, activate.click_mode()));
+        picker.list.gridview.connect_activate(move |_, pos| controls.accept(pos, controls.click_mode()));
     }
 
     {
@@ -399,3 +1374,24 @@ fn app_picker_control(picker: &AppPicker) {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_query_takes_args_after_dashes() {
+        assert_eq!(split_query("fire"), ("fire", vec![]));
+        assert_eq!(
+            split_query("code -- a 'b c'"),
+            ("code", vec!["a".to_owned(), "b c".to_owned()])
+        );
+        assert_eq!(split_query("code  --"), ("code", vec![]));
+        assert_eq!(split_query("code --new"), ("code --new", vec![]));
+        // only the first ` -- ` splits
+        assert_eq!(
+            split_query("sh -- -c -- x"),
+            ("sh", vec!["-c".to_owned(), "--".to_owned(), "x".to_owned()])
+        );
+    }
+}
@@ -19,6 +19,11 @@ pub trait Picker {
     fn show(&self, current: PickerKind) -> bool;
     fn kind(&self) -> PickerKind;
     fn reload(&self, config: &ConfigLoad);
+    fn refresh(&self);
+    /// Restyle rows after a launch starts or exits
+    fn update_running(&self);
+    /// Reset and close, like pressing Escape
+    fn cancel(&self);
     fn if_done(&self, callback: Box<dyn Fn()>);
 }
 
@@ -35,7 +40,17 @@ impl PickerKind {
     }
 }
 
-pub(crate) fn create_picker_components() -> (gtk::Box, gtk::Entry, gtk::Button, gtk::ListBox) {
+pub(crate) struct PickerComponents {
+    pub mainbox: gtk::Box,
+    pub search_input: gtk::Entry,
+    pub toggle_btn: gtk::Button,
+    pub scroll: gtk::ScrolledWindow,
+    pub listview: gtk::ListView,
+    pub gridview: gtk::GridView,
+    pub preview: gtk::Box,
+}
+
+pub(crate) fn create_picker_components() -> PickerComponents {
     let mainbox = gtk::Box::builder()
         .name("main-box")
         .orientation(gtk::Orientation::Vertical)
@@ -71,18 +86,55 @@ pub(crate) fn create_picker_components() -> (gtk::Box, gtk::Entry, gtk::Button,
         .build();
     scroll_apps.set_vexpand(true);
 
-    let listbox = gtk::ListBox::builder()
+    // model and factory are set by the picker
+    let listview = gtk::ListView::builder()
         .name("picker-list")
-        .selection_mode(gtk::SelectionMode::Single)
+        .single_click_activate(true)
+        .vexpand_set(true)
+        .build();
+
+    // same, shown instead of the list with `layout = "grid"`
+    let gridview = gtk::GridView::builder()
+        .name("picker-grid")
+        .single_click_activate(true)
         .vexpand_set(true)
         .build();
 
+    let content = gtk::Box::builder()
+        .name("picker-content")
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(0)
+        .build();
+    scroll_apps.set_hexpand(true);
+
+    // filled by the picker, hidden until toggled
+    let preview = gtk::Box::builder()
+        .name("preview-pane")
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(8)
+        .width_request(260)
+        .margin_end(10)
+        .margin_top(10)
+        .margin_bottom(10)
+        .visible(false)
+        .build();
+
     // Assemble the UI
     box_input.append(&search_input);
     box_input.append(&toggle_btn);
-    scroll_apps.set_child(Some(&listbox));
+    scroll_apps.set_child(Some(&listview));
+    content.append(&scroll_apps);
+    content.append(&preview);
     mainbox.append(&box_input);
-    mainbox.append(&scroll_apps);
+    mainbox.append(&content);
 
-    (mainbox, search_input, toggle_btn, listbox)
+    PickerComponents {
+        mainbox,
+        search_input,
+        toggle_btn,
+        scroll: scroll_apps,
+        listview,
+        gridview,
+        preview,
+    }
 }
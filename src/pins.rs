@@ -0,0 +1,91 @@
+use crate::misc;
+
+use std::path::PathBuf;
+
+/// Pinned apps by id, from config (fixed) and runtime (persisted to the state file)
+pub(crate) struct Pins {
+    config: Vec<String>,
+    runtime: Vec<String>,
+    path: PathBuf,
+}
+
+impl Pins {
+    pub fn load(config: &[String]) -> Self {
+        let path = misc::get_state_path("pinned");
+        let runtime = std::fs::read_to_string(&path)
+            .map(|data| {
+                data.lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(str::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            config: config.to_vec(),
+            runtime,
+            path,
+        }
+    }
+
+    pub fn set_config(&mut self, config: &[String]) {
+        self.config = config.to_vec();
+    }
+
+    pub fn is_pinned(&self, id: &str) -> bool {
+        self.config.iter().chain(&self.runtime).any(|p| p == id)
+    }
+
+    pub fn is_config_pinned(&self, id: &str) -> bool {
+        self.config.iter().any(|p| p == id)
+    }
+
+    /// Position in the pinned section, config pins go first
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.config.iter().chain(&self.runtime).position(|p| p == id)
+    }
+
+    pub fn pin(&mut self, id: &str) -> bool {
+        if self.is_pinned(id) {
+            return false;
+        }
+        self.runtime.push(id.to_owned());
+        self.save();
+        true
+    }
+
+    pub fn unpin(&mut self, id: &str) -> bool {
+        let len = self.runtime.len();
+        self.runtime.retain(|p| p != id);
+        if self.runtime.len() == len {
+            return false;
+        }
+        self.save();
+        true
+    }
+
+    /// Whether it's pinned now, `None` if the config pins it and it can't be unpinned here
+    pub fn toggle(&mut self, id: &str) -> Option<bool> {
+        if self.is_config_pinned(id) {
+            None
+        } else if self.is_pinned(id) {
+            self.unpin(id);
+            Some(false)
+        } else {
+            Some(self.pin(id))
+        }
+    }
+
+    fn save(&self) {
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+
+        let mut data = self.runtime.join("\n");
+        data.push('\n');
+        if let Err(e) = std::fs::write(&self.path, data) {
+            eprintln!("Error writing state file {}: {}", self.path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::config::{Anchor, WindowConfig};
+use crate::gtk;
+
+use gtk::prelude::*;
+use gtk::{gdk, glib, ApplicationWindow};
+
+/// Monitor the window should open on
+///
+/// A configured connector wins, otherwise it's the monitor the window was last shown on, which
+/// the window manager picks from the pointer or keyboard focus. Before the first show there's
+/// nothing to go by, so the first monitor is used and `follow_monitor` fixes it up once mapped
+pub(crate) fn target_monitor(window: &ApplicationWindow, config: &WindowConfig) -> Option<gdk::Monitor> {
+    let display = gdk::Display::default()?;
+
+    if let Some(connector) = &config.monitor {
+        match find_monitor(&display, connector) {
+            Some(monitor) => return Some(monitor),
+            None => eprintln!("No monitor named {connector}, using the focused one"),
+        }
+    }
+
+    window
+        .surface()
+        .and_then(|surface| display.monitor_at_surface(&surface))
+        .or_else(|| monitors(&display).next())
+}
+
+pub(crate) fn fit_to_monitor(window: &ApplicationWindow, config: &WindowConfig, monitor: &gdk::Monitor) {
+    let geom = monitor.geometry();
+    window.set_default_size(config.width.resolve(geom.width()), config.height.resolve(geom.height()));
+}
+
+/// Size the window before it's shown, layer surfaces are anchored and moved too
+pub(crate) fn place(window: &ApplicationWindow, config: &WindowConfig) {
+    #[cfg(feature = "layer-shell")]
+    if layer::place(window, config) {
+        return;
+    }
+
+    if let Some(monitor) = target_monitor(window, config) {
+        fit_to_monitor(window, config, &monitor);
+    }
+}
+
+/// Say once that `monitor`, `anchor` and the margins can't place a plain window
+pub(crate) fn warn_unplaced(window: &ApplicationWindow, config: &WindowConfig) {
+    #[cfg(feature = "layer-shell")]
+    if gtk4_layer_shell::LayerShell::is_layer_window(window) {
+        return;
+    }
+    #[cfg(not(feature = "layer-shell"))]
+    let _ = window;
+
+    let margins = [
+        config.margin_top,
+        config.margin_bottom,
+        config.margin_left,
+        config.margin_right,
+    ];
+    let set: Vec<&str> = [
+        ("monitor", config.monitor.is_some()),
+        ("anchor", config.anchor != Anchor::Center),
+        ("margins", margins.iter().any(|m| *m != 0)),
+    ]
+    .into_iter()
+    .filter_map(|(name, set)| set.then_some(name))
+    .collect();
+
+    if !set.is_empty() {
+        eprintln!(
+            "Window {} need the layer-shell overlay, a plain window only takes its size from the monitor",
+            set.join(", ")
+        );
+    }
+}
+
+/// Resize whenever the window lands on another monitor, `config` is read when that happens
+pub(crate) fn follow_monitor<F>(window: &ApplicationWindow, config: F)
+where
+    F: Fn() -> WindowConfig + Clone + 'static,
+{
+    window.connect_realize(move |window| {
+        let Some(surface) = window.surface() else {
+            return;
+        };
+
+        let window = window.downgrade();
+        let config = config.clone();
+        surface.connect_enter_monitor(move |_, monitor| {
+            let (window, monitor, config) = (window.clone(), monitor.clone(), config.clone());
+
+            // this fires while the window is being shown, let that finish first
+            glib::idle_add_local_once(move || {
+                let config = config();
+                if let (Some(window), None) = (window.upgrade(), &config.monitor) {
+                    fit_to_monitor(&window, &config, &monitor);
+                }
+            });
+        });
+    });
+}
+
+fn find_monitor(display: &gdk::Display, connector: &str) -> Option<gdk::Monitor> {
+    monitors(display).find(|m| m.connector().is_some_and(|c| c.eq_ignore_ascii_case(connector)))
+}
+
+fn monitors(display: &gdk::Display) -> impl Iterator<Item = gdk::Monitor> {
+    let model = display.monitors();
+    (0..model.n_items()).filter_map(move |i| model.item(i)?.downcast::<gdk::Monitor>().ok())
+}
+
+#[cfg(feature = "layer-shell")]
+pub(crate) use layer::init_layer_shell;
+
+#[cfg(feature = "layer-shell")]
+mod layer {
+    use super::*;
+
+    use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
+
+    /// Turn the window into an overlay layer surface, must run before it's realized
+    ///
+    /// Compositors without wlr-layer-shell keep the normal window
+    pub(crate) fn init_layer_shell(window: &ApplicationWindow) {
+        if !gtk4_layer_shell::is_supported() {
+            eprintln!("Compositor has no layer-shell support, using a normal window");
+            return;
+        }
+
+        window.init_layer_shell();
+        window.set_namespace(Some("gall"));
+        window.set_layer(Layer::Overlay);
+        window.set_keyboard_mode(KeyboardMode::Exclusive);
+    }
+
+    /// Returns false if the window isn't a layer surface
+    pub(super) fn place(window: &ApplicationWindow, config: &WindowConfig) -> bool {
+        if !window.is_layer_window() {
+            return false;
+        }
+
+        let edges: &[Edge] = match config.anchor {
+            Anchor::Center => &[],
+            Anchor::Top => &[Edge::Top],
+            Anchor::Bottom => &[Edge::Bottom],
+            Anchor::Left => &[Edge::Left],
+            Anchor::Right => &[Edge::Right],
+            Anchor::TopLeft => &[Edge::Top, Edge::Left],
+            Anchor::TopRight => &[Edge::Top, Edge::Right],
+            Anchor::BottomLeft => &[Edge::Bottom, Edge::Left],
+            Anchor::BottomRight => &[Edge::Bottom, Edge::Right],
+        };
+
+        for (edge, margin) in [
+            (Edge::Top, config.margin_top),
+            (Edge::Bottom, config.margin_bottom),
+            (Edge::Left, config.margin_left),
+            (Edge::Right, config.margin_right),
+        ] {
+            window.set_anchor(edge, edges.contains(&edge));
+            window.set_margin(edge, margin);
+        }
+
+        // without a monitor the compositor picks the focused output, follow_monitor sizes it
+        let display = gdk::Display::default();
+        let fixed = config
+            .monitor
+            .as_deref()
+            .zip(display.as_ref())
+            .and_then(|(connector, display)| find_monitor(display, connector));
+        window.set_monitor(fixed.as_ref());
+
+        if let Some(monitor) = fixed.or_else(|| target_monitor(window, config)) {
+            fit_to_monitor(window, config, &monitor);
+        }
+
+        true
+    }
+}
@@ -0,0 +1,114 @@
+use crate::gtk::glib;
+use crate::launcher::Exit;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// exited entries kept around for `gall ps`
+const KEEP_EXITED: usize = 20;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ProcessState {
+    Running,
+    /// SIGTERM was sent, waiting for it to exit
+    Stopping,
+    Exited(Exit),
+}
+
+#[derive(Debug)]
+pub(crate) struct RunningApp {
+    pub pid: u32,
+    pub id: String,
+    pub exec: String,
+    /// Unix time it was launched
+    pub started: i64,
+    pub state: ProcessState,
+}
+
+impl RunningApp {
+    fn is_alive(&self) -> bool {
+        !matches!(self.state, ProcessState::Exited(_))
+    }
+}
+
+/// Processes launched by this daemon, updated as they're reaped
+#[derive(Default)]
+pub(crate) struct Running {
+    apps: Vec<RunningApp>,
+}
+
+impl Running {
+    pub fn add(&mut self, pid: u32, id: &str, exec: &str) {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+
+        self.apps.push(RunningApp {
+            pid,
+            id: id.to_owned(),
+            exec: exec.to_owned(),
+            started,
+            state: ProcessState::Running,
+        });
+    }
+
+    pub fn exited(&mut self, pid: u32, exit: Exit) {
+        if let Some(app) = self.apps.iter_mut().find(|a| a.pid == pid && a.is_alive()) {
+            app.state = ProcessState::Exited(exit);
+        }
+
+        let exited = self.apps.iter().filter(|a| !a.is_alive()).count();
+        let mut extra = exited.saturating_sub(KEEP_EXITED);
+        self.apps.retain(|a| {
+            let drop = extra > 0 && !a.is_alive();
+            extra -= drop as usize;
+            !drop
+        });
+    }
+
+    pub fn is_running(&self, id: &str) -> bool {
+        self.apps.iter().any(|a| a.id == id && a.is_alive())
+    }
+
+    /// Send `signal` to every live process group launched for `id`, returns how many
+    ///
+    /// Launches are session leaders, so this reaches whatever they started too
+    pub fn signal(&mut self, id: &str, signal: i32) -> usize {
+        let mut sent = 0;
+        for app in self.apps.iter_mut().filter(|a| a.id == id && a.is_alive()) {
+            if unsafe { libc::kill(-(app.pid as i32), signal) } == 0 {
+                sent += 1;
+                if signal == libc::SIGTERM {
+                    app.state = ProcessState::Stopping;
+                }
+            }
+        }
+        sent
+    }
+
+    /// Table for `gall ps`, oldest first
+    pub fn table(&self) -> String {
+        let mut out = format!("{:<8} {:<10} {:<20} {:<24} {}\n", "PID", "STATE", "STARTED", "APP", "COMMAND");
+
+        for app in &self.apps {
+            let state = match app.state {
+                ProcessState::Running => "running".to_owned(),
+                ProcessState::Stopping => "stopping".to_owned(),
+                ProcessState::Exited(exit) => match (exit.code, exit.signal) {
+                    (Some(code), _) => format!("exit {code}"),
+                    (_, Some(signal)) => format!("signal {signal}"),
+                    _ => "exited".to_owned(),
+                },
+            };
+            let started = glib::DateTime::from_unix_local(app.started)
+                .and_then(|d| d.format("%F %T"))
+                .map_or_else(|_| app.started.to_string(), |d| d.to_string());
+
+            out.push_str(&format!(
+                "{:<8} {:<10} {:<20} {:<24} {}\n",
+                app.pid, state, started, app.id, app.exec
+            ));
+        }
+
+        out
+    }
+}
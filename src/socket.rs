@@ -1,8 +1,9 @@
-use crate::{Arc, Mutex};
+use crate::pins::Pins;
+use crate::running::Running;
+use crate::{AppState, Arc, Mutex};
 
 use std::collections::VecDeque;
 use std::io::{Read, Write};
-use std::mem::{align_of, size_of};
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
@@ -12,45 +13,65 @@ use std::time::Duration;
 pub(crate) type MessageQueue = Arc<Mutex<VecDeque<Vec<u8>>>>;
 static SOCKET_PATH: OnceLock<PathBuf> = OnceLock::new();
 
-#[repr(C)]
 #[derive(Debug)]
-pub enum AppMessage {
+pub(crate) enum AppMessage {
     TogglePicker(crate::pickers::PickerKind),
     AppPing,
     AppClose,
     AppReload,
+    PinApp(String),
+    UnpinApp(String),
+    SetTheme(String),
+    /// Asks for the `running::Running` table, answered by the listener
+    ListRunning,
+    ShowLog,
 }
-impl From<Vec<u8>> for AppMessage {
-    fn from(bytes: Vec<u8>) -> Self {
-        assert_eq!(bytes.len(), size_of::<AppMessage>(), "Wrong size");
-        assert_eq!(
-            bytes.as_ptr() as usize % align_of::<AppMessage>(),
-            0,
-            "Misaligned buffer"
-        );
-
-        unsafe { (bytes.as_ptr() as *const AppMessage).read_unaligned() }
-    }
-}
 
-impl From<&[u8]> for AppMessage {
-    fn from(bytes: &[u8]) -> Self {
-        assert_eq!(bytes.len(), size_of::<AppMessage>());
-        assert_eq!(
-            bytes.as_ptr() as usize % align_of::<AppMessage>(),
-            0,
-            "Misaligned buffer"
-        );
+// Wire format: one tag byte followed by the UTF-8 payload (if any)
+impl TryFrom<&[u8]> for AppMessage {
+    type Error = String;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let (&tag, payload) = bytes.split_first().ok_or("Empty message")?;
+        let text = || String::from_utf8(payload.to_vec()).map_err(|_| "Payload is not UTF-8".to_owned());
 
-        unsafe { (bytes.as_ptr() as *const AppMessage).read() }
+        match tag {
+            0 => {
+                let &[kind] = payload else {
+                    return Err("Bad picker kind".into());
+                };
+                crate::pickers::PickerKind::variants()
+                    .into_iter()
+                    .find(|k| *k as u8 == kind)
+                    .map(AppMessage::TogglePicker)
+                    .ok_or_else(|| format!("Unknown picker kind {kind}"))
+            }
+            1 => Ok(AppMessage::AppPing),
+            2 => Ok(AppMessage::AppClose),
+            3 => Ok(AppMessage::AppReload),
+            4 => Ok(AppMessage::PinApp(text()?)),
+            5 => Ok(AppMessage::UnpinApp(text()?)),
+            6 => Ok(AppMessage::SetTheme(text()?)),
+            7 => Ok(AppMessage::ListRunning),
+            8 => Ok(AppMessage::ShowLog),
+            _ => Err(format!("Unknown message tag {tag}")),
+        }
     }
 }
 
-impl Into<Vec<u8>> for AppMessage {
-    fn into(self) -> Vec<u8> {
-        let size = size_of::<Self>();
-        let ptr = &self as *const Self as *const u8;
-        unsafe { std::slice::from_raw_parts(ptr, size).to_vec() }
+impl From<AppMessage> for Vec<u8> {
+    fn from(msg: AppMessage) -> Self {
+        match msg {
+            AppMessage::TogglePicker(kind) => vec![0, kind as u8],
+            AppMessage::AppPing => vec![1],
+            AppMessage::AppClose => vec![2],
+            AppMessage::AppReload => vec![3],
+            AppMessage::PinApp(id) => [&[4], id.as_bytes()].concat(),
+            AppMessage::UnpinApp(id) => [&[5], id.as_bytes()].concat(),
+            AppMessage::SetTheme(name) => [&[6], name.as_bytes()].concat(),
+            AppMessage::ListRunning => vec![7],
+            AppMessage::ShowLog => vec![8],
+        }
     }
 }
 
@@ -61,7 +82,12 @@ pub fn get_socket_path() -> &'static PathBuf {
     })
 }
 
-pub fn start_socket_listener(message_queue: MessageQueue) {
+pub fn start_socket_listener(
+    message_queue: MessageQueue,
+    running: Arc<Mutex<Running>>,
+    pins: Arc<Mutex<Pins>>,
+    state: Arc<Mutex<AppState>>,
+) {
     let listener = match UnixListener::bind(get_socket_path()) {
         Ok(listener) => listener,
         Err(_) => {
@@ -76,7 +102,10 @@ pub fn start_socket_listener(message_queue: MessageQueue) {
         match listener.accept() {
             Ok((stream, _)) => {
                 let queue = Arc::clone(&message_queue);
-                thread::spawn(move || handle_client(stream, queue));
+                let running = Arc::clone(&running);
+                let pins = Arc::clone(&pins);
+                let state = Arc::clone(&state);
+                thread::spawn(move || handle_client(stream, queue, running, &pins, &state));
             }
             Err(_) => {
                 if let Ok(mut queue) = message_queue.lock() {
@@ -89,29 +118,71 @@ pub fn start_socket_listener(message_queue: MessageQueue) {
     }
 }
 
-pub fn handle_client(mut stream: UnixStream, message_queue: MessageQueue) {
+pub fn handle_client(
+    mut stream: UnixStream,
+    message_queue: MessageQueue,
+    running: Arc<Mutex<Running>>,
+    pins: &Mutex<Pins>,
+    state: &Mutex<AppState>,
+) {
     let mut buffer = [0; 1024];
 
     match stream.read(&mut buffer) {
-        Ok(n) => {
-            if n == std::mem::size_of::<AppMessage>() {
-                match AppMessage::from(buffer[..n].to_vec()) {
-                    AppMessage::AppPing => {
-                        let response: Vec<u8> = AppMessage::AppPing.into();
-                        let _ = stream.write_all(&response);
-                    }
-                    msg => {
-                        if let Ok(mut queue) = message_queue.lock() {
-                            queue.push_back(msg.into());
-                        }
-                    }
+        Ok(n) => match AppMessage::try_from(&buffer[..n]) {
+            Ok(AppMessage::AppPing) => {
+                let response: Vec<u8> = AppMessage::AppPing.into();
+                let _ = stream.write_all(&response);
+            }
+            Ok(AppMessage::ListRunning) => {
+                let table = running.lock().map(|r| r.table()).unwrap_or_default();
+                let _ = stream.write_all(table.as_bytes());
+            }
+            Ok(msg @ (AppMessage::PinApp(_) | AppMessage::UnpinApp(_))) => {
+                let reply = change_pins(&msg, pins, state);
+                let _ = stream.write_all(reply.as_bytes());
+                // the main loop only refreshes the pickers for these
+                if reply.is_empty()
+                    && let Ok(mut queue) = message_queue.lock()
+                {
+                    queue.push_back(msg.into());
                 }
             }
-        }
+            Ok(msg) => {
+                if let Ok(mut queue) = message_queue.lock() {
+                    queue.push_back(msg.into());
+                }
+            }
+            Err(e) => eprintln!("Dropping bad message: {e}"),
+        },
         Err(_) => (),
     }
 }
 
+// `gall pin`/`gall unpin` are answered here so the CLI can say why nothing changed, an empty
+// reply means it worked
+fn change_pins(msg: &AppMessage, pins: &Mutex<Pins>, state: &Mutex<AppState>) -> String {
+    match msg {
+        AppMessage::PinApp(id) => {
+            let known = state.lock().is_ok_and(|s| s.config.apps.iter().any(|a| a.id() == id));
+            if !known {
+                return format!("Unknown app {id}");
+            }
+            pins.lock().unwrap().pin(id);
+        }
+        AppMessage::UnpinApp(id) => {
+            let mut pins = pins.lock().unwrap();
+            if pins.is_config_pinned(id) {
+                return format!("{id} is pinned in the config file, remove it there");
+            }
+            if !pins.unpin(id) {
+                return format!("{id} is not pinned");
+            }
+        }
+        _ => (),
+    }
+    String::new()
+}
+
 pub fn send_message(message: AppMessage) -> Result<(), Box<dyn std::error::Error>> {
     if !process_is_running() {
         return Err("Process is dead!".into());
@@ -122,6 +193,20 @@ pub fn send_message(message: AppMessage) -> Result<(), Box<dyn std::error::Error
     Ok(())
 }
 
+/// Send `message` and read the reply until the daemon hangs up
+pub fn request(message: AppMessage) -> Result<String, Box<dyn std::error::Error>> {
+    if !process_is_running() {
+        return Err("Process is dead!".into());
+    }
+    let mut stream = UnixStream::connect(get_socket_path())?;
+    stream.write_all(Into::<Vec<u8>>::into(message).as_slice())?;
+    stream.flush()?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    Ok(reply)
+}
+
 pub fn process_is_running() -> bool {
     if !Path::new(get_socket_path()).exists() {
         return false;
@@ -135,15 +220,49 @@ pub fn process_is_running() -> bool {
                 return false;
             }
 
-            let mut buffer = [0u8; std::mem::size_of::<AppMessage>()];
-            match stream.read_exact(&mut buffer) {
-                Ok(_) => {
-                    let reply = AppMessage::from(buffer.to_vec());
-                    matches!(reply, AppMessage::AppPing)
-                }
+            let mut buffer = [0u8; 16];
+            match stream.read(&mut buffer) {
+                Ok(n) => matches!(AppMessage::try_from(&buffer[..n]), Ok(AppMessage::AppPing)),
                 Err(_) => false,
             }
         }
         Err(_) => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pickers::PickerKind;
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            AppMessage::TogglePicker(PickerKind::Apps),
+            AppMessage::AppPing,
+            AppMessage::AppClose,
+            AppMessage::AppReload,
+            AppMessage::PinApp("firefox.desktop".to_owned()),
+            AppMessage::UnpinApp("ünïcode app".to_owned()),
+            AppMessage::SetTheme(String::new()),
+            AppMessage::ListRunning,
+            AppMessage::ShowLog,
+        ];
+
+        for msg in messages {
+            let expected = format!("{msg:?}");
+            let bytes: Vec<u8> = msg.into();
+            let parsed = AppMessage::try_from(bytes.as_slice()).unwrap();
+            assert_eq!(format!("{parsed:?}"), expected);
+        }
+    }
+
+    #[test]
+    fn bad_messages_are_rejected() {
+        assert!(AppMessage::try_from(&[][..]).is_err());
+        assert!(AppMessage::try_from(&[42][..]).is_err());
+        assert!(AppMessage::try_from(&[0][..]).is_err());
+        assert!(AppMessage::try_from(&[0, PickerKind::None as u8][..]).is_err());
+        assert!(AppMessage::try_from(&[4, 0xff, 0xfe][..]).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use crate::misc;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct LaunchCount {
+    pub count: u32,
+    /// Unix time of the last launch
+    pub last: i64,
+}
+
+/// Launch counts by app id, persisted to the state file
+pub(crate) struct LaunchStats {
+    launches: HashMap<String, LaunchCount>,
+    path: PathBuf,
+}
+
+impl LaunchStats {
+    pub fn load() -> Self {
+        let path = misc::get_state_path("launches");
+        let launches = std::fs::read_to_string(&path)
+            .map(|data| {
+                data.lines()
+                    .filter_map(|line| {
+                        let mut fields = line.split('\t');
+                        let id = fields.next()?.to_owned();
+                        let count = fields.next()?.parse().ok()?;
+                        let last = fields.next()?.parse().ok()?;
+                        Some((id, LaunchCount { count, last }))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self { launches, path }
+    }
+
+    pub fn get(&self, id: &str) -> Option<LaunchCount> {
+        self.launches.get(id).copied()
+    }
+
+    pub fn record(&mut self, id: &str) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+
+        let entry = self.launches.entry(id.to_owned()).or_default();
+        entry.count += 1;
+        entry.last = now;
+        self.save();
+    }
+
+    fn save(&self) {
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+
+        let data: String = self
+            .launches
+            .iter()
+            .map(|(id, l)| format!("{}\t{}\t{}\n", id, l.count, l.last))
+            .collect();
+        if let Err(e) = std::fs::write(&self.path, data) {
+            eprintln!("Error writing state file {}: {}", self.path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::gtk;
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+
+use gtk::prelude::*;
+use gtk::{gdk, gio};
+
+/// Themes bundled in the binary, `data/themes/<name>.css`
+pub(crate) const THEMES: [&str; 4] = ["light", "dark", "compact", "spotlight"];
+const THEMES_PATH: &str = "/xyz/gall/pickers/themes";
+
+thread_local! {
+    static THEME: RefCell<Option<gtk::CssProvider>> = const { RefCell::new(None) };
+    static USER: RefCell<Vec<gtk::CssProvider>> = const { RefCell::new(Vec::new()) };
+}
+
+pub(crate) fn register_resources() {
+    gio::resources_register_include!("gall.gresource").expect("bundled resources to be valid");
+}
+
+/// Load the user CSS, replacing what the last call loaded
+///
+/// `path` is a CSS file or a directory of them, which are loaded in file name order so later
+/// files override earlier ones (`00-base.css`, `10-colors.css`, ...)
+pub(crate) fn apply_styles(path: &Path) {
+    let Some(display) = gdk::Display::default() else {
+        eprintln!("No display found for applying CSS.");
+        return;
+    };
+
+    for old in USER.take() {
+        gtk::style_context_remove_provider_for_display(&display, &old);
+    }
+
+    let providers = style_files(path)
+        .into_iter()
+        .map(|file| {
+            let provider = gtk::CssProvider::new();
+            let loaded = file.clone();
+            provider.connect_parsing_error(move |_, section, error| {
+                // errors in @import-ed files point at those
+                let source = section.file().and_then(|f| f.path()).unwrap_or_else(|| loaded.clone());
+                let start = section.start_location();
+                eprintln!(
+                    "CSS error in {}:{}:{}: {}",
+                    source.display(),
+                    start.lines() + 1,
+                    start.line_chars() + 1,
+                    error.message()
+                );
+            });
+
+            provider.load_from_path(&file);
+            gtk::style_context_add_provider_for_display(&display, &provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+            provider
+        })
+        .collect();
+
+    USER.set(providers);
+}
+
+/// CSS files `apply_styles` loads for `path`, a missing path is fine and means no user styles
+pub(crate) fn style_files(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return if path.exists() { vec![path.to_path_buf()] } else { Vec::new() };
+    }
+
+    let mut files: Vec<PathBuf> = match std::fs::read_dir(path) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "css"))
+            .collect(),
+        Err(e) => {
+            eprintln!("Error reading styles directory {}: {e}", path.display());
+            Vec::new()
+        }
+    };
+
+    files.sort();
+    files
+}
+
+/// Swap the bundled theme under the user CSS, `None` or "none" leaves plain GTK styling
+pub(crate) fn set_theme(name: Option<&str>) {
+    let Some(display) = gdk::Display::default() else {
+        eprintln!("No display found for applying CSS.");
+        return;
+    };
+
+    if let Some(old) = THEME.take() {
+        gtk::style_context_remove_provider_for_display(&display, &old);
+    }
+
+    let name = match name {
+        None | Some("none") => return,
+        Some(name) if THEMES.contains(&name) => name,
+        Some(name) => {
+            eprintln!("Unknown theme {name}, available: {}", THEMES.join(", "));
+            return;
+        }
+    };
+
+    let provider = gtk::CssProvider::new();
+    provider.load_from_resource(&format!("{THEMES_PATH}/{name}.css"));
+
+    // one step under the user CSS, so it wins any rule both set
+    gtk::style_context_add_provider_for_display(&display, &provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION - 1);
+    THEME.set(Some(provider));
+}
+
+/// Local files pulled in with `@import`, followed through nested imports
+pub(crate) fn imported_files(css_file: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut queue = vec![css_file.to_path_buf()];
+
+    while let Some(file) = queue.pop() {
+        let Ok(content) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+        let dir = file.parent().unwrap_or(Path::new("/"));
+
+        for line in content.lines() {
+            let Some(target) = line.trim().strip_prefix("@import").and_then(import_target) else {
+                continue;
+            };
+
+            let path = dir.join(target.strip_prefix("file://").unwrap_or(target));
+            if path != css_file && !found.contains(&path) {
+                found.push(path.clone());
+                queue.push(path);
+            }
+        }
+    }
+
+    found
+}
+
+// `url("a.css")`, `url(a.css)` or `"a.css"`, remote urls are skipped
+fn import_target(rule: &str) -> Option<&str> {
+    let rule = rule.trim().trim_end_matches(';').trim();
+    let rule = match rule.strip_prefix("url(") {
+        Some(inner) => inner.split(')').next()?.trim(),
+        None => rule,
+    };
+
+    let target = rule.trim_matches(|c| c == '"' || c == '\'');
+    (!target.is_empty() && (!target.contains("://") || target.starts_with("file://"))).then_some(target)
+}
@@ -0,0 +1,138 @@
+use std::path::Path;
+
+/// Known terminals and how they take a command, also the autodetection order
+pub(crate) const PRESETS: &[(&str, &str)] = &[
+    ("foot", "foot {argv}"),
+    ("alacritty", "alacritty -e {argv}"),
+    ("kitty", "kitty {argv}"),
+    ("wezterm", "wezterm start -- {argv}"),
+    ("ghostty", "ghostty -e {argv}"),
+    ("gnome-terminal", "gnome-terminal -- {argv}"),
+    ("konsole", "konsole -e {argv}"),
+    ("xfce4-terminal", "xfce4-terminal -x {argv}"),
+    ("tilix", "tilix -e {cmd}"),
+    ("urxvt", "urxvt -e {argv}"),
+    ("st", "st -e {argv}"),
+    ("xterm", "xterm -e {argv}"),
+    ("x-terminal-emulator", "x-terminal-emulator -e {argv}"),
+];
+
+/// Template for the `terminal` setting: a preset name, a template, or a bare command that gets
+/// the argv appended. Unset tries `$TERMINAL`, then the presets found in `PATH`; empty disables
+pub(crate) fn resolve(terminal: Option<&str>) -> Option<String> {
+    match terminal.map(str::trim) {
+        Some("") => None,
+        Some(terminal) => Some(template(terminal)),
+        None => std::env::var("TERMINAL")
+            .ok()
+            .filter(|t| !t.trim().is_empty())
+            .map(|t| template(t.trim()))
+            .or_else(detect),
+    }
+}
+
+fn template(terminal: &str) -> String {
+    // `/usr/bin/alacritty` is the preset too, but not `alacritty --class x`
+    if !terminal.contains(char::is_whitespace) {
+        let name = Path::new(terminal)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(terminal);
+        if let Some((_, preset)) = PRESETS.iter().find(|(preset, _)| *preset == name) {
+            return preset.replacen(name, terminal, 1);
+        }
+    }
+    if terminal.contains("{argv}") || terminal.contains("{cmd}") {
+        return terminal.to_owned();
+    }
+    format!("{terminal} {{argv}}")
+}
+
+fn detect() -> Option<String> {
+    let path = std::env::var_os("PATH")?;
+    let dirs: Vec<_> = std::env::split_paths(&path).collect();
+
+    PRESETS
+        .iter()
+        .find(|(name, _)| dirs.iter().any(|dir| is_executable(&dir.join(name))))
+        .map(|(_, preset)| (*preset).to_owned())
+}
+
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+/// `exec` run through `template`, for `sh -c`
+///
+/// `{argv}` becomes `sh -c '<exec>'` as separate arguments, for terminals that exec what
+/// follows their flag. `{cmd}` is the whole of `exec` as one argument, for those that take a
+/// command line
+pub(crate) fn command(template: &str, exec: &str) -> String {
+    // one pass, so a `{cmd}` in `exec` isn't replaced too
+    let mut command = String::with_capacity(template.len() + exec.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        command.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("{argv}") {
+            command.push_str("sh -c ");
+            command.push_str(&quote(exec));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{cmd}") {
+            command.push_str(&quote(exec));
+            rest = after;
+        } else {
+            command.push('{');
+            rest = &rest[1..];
+        }
+    }
+    command.push_str(rest);
+    command
+}
+
+/// Single quote `arg` for `sh`
+pub(crate) fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_uses_presets_by_file_name() {
+        assert_eq!(template("alacritty"), "alacritty -e {argv}");
+        assert_eq!(template("/usr/bin/alacritty"), "/usr/bin/alacritty -e {argv}");
+        assert_eq!(template("tilix"), "tilix -e {cmd}");
+        // anything with arguments is a command, not a preset
+        assert_eq!(template("alacritty --class x"), "alacritty --class x {argv}");
+        assert_eq!(
+            template("wezterm start --config-file ~/.config/foot"),
+            "wezterm start --config-file ~/.config/foot {argv}"
+        );
+        assert_eq!(template("myterm -x {cmd}"), "myterm -x {cmd}");
+    }
+
+    #[test]
+    fn command_substitutes_once() {
+        assert_eq!(command("foot {argv}", "htop"), "foot sh -c 'htop'");
+        assert_eq!(
+            command("tilix -e {cmd}", "vim 'a b'"),
+            r"tilix -e 'vim '\''a b'\'''"
+        );
+        assert_eq!(command("t {cmd}", "echo {argv}"), "t 'echo {argv}'");
+        assert_eq!(
+            command("t --title {x} {argv}", "top"),
+            "t --title {x} sh -c 'top'"
+        );
+    }
+
+    #[test]
+    fn quote_escapes_single_quotes() {
+        assert_eq!(quote("plain"), "'plain'");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+    }
+}
@@ -0,0 +1,134 @@
+use crate::gtk;
+use crate::styles;
+
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use gtk::prelude::*;
+use gtk::{gio, glib};
+
+// editors write in bursts (temp file, rename, chmod), wait for them to settle
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Changed {
+    Config,
+    Styles,
+}
+
+/// Watches the config file, the styles file or directory and their `@import`s
+///
+/// Changes are collected until nothing has happened for a moment, then `on_change` runs
+/// once per kind
+pub(crate) struct Watcher {
+    config_path: PathBuf,
+    styles_path: PathBuf,
+    monitors: RefCell<Vec<gio::FileMonitor>>,
+    pending: RefCell<Option<glib::SourceId>>,
+    config_changed: Cell<bool>,
+    styles_changed: Cell<bool>,
+    on_change: Box<dyn Fn(Changed)>,
+}
+
+impl Watcher {
+    pub fn new<F: Fn(Changed) + 'static>(config_path: &Path, styles_path: &Path, on_change: F) -> Rc<Self> {
+        let watcher = Rc::new(Self {
+            config_path: config_path.to_path_buf(),
+            styles_path: styles_path.to_path_buf(),
+            monitors: RefCell::new(Vec::new()),
+            pending: RefCell::new(None),
+            config_changed: Cell::new(false),
+            styles_changed: Cell::new(false),
+            on_change: Box::new(on_change),
+        });
+
+        watcher.rewatch();
+        watcher
+    }
+
+    // imports can come and go with every styles change, so the set is rebuilt each time
+    fn rewatch(self: &Rc<Self>) {
+        let mut monitors = vec![];
+        self.monitor(&mut monitors, &self.config_path, Changed::Config);
+        self.monitor(&mut monitors, &self.styles_path, Changed::Styles);
+
+        for file in styles::style_files(&self.styles_path) {
+            for import in styles::imported_files(&file) {
+                self.monitor(&mut monitors, &import, Changed::Styles);
+            }
+        }
+
+        for old in self.monitors.replace(monitors) {
+            old.cancel();
+        }
+    }
+
+    fn monitor(self: &Rc<Self>, monitors: &mut Vec<gio::FileMonitor>, path: &Path, kind: Changed) {
+        let file = gio::File::for_path(path);
+        let flags = gio::FileMonitorFlags::WATCH_MOVES;
+
+        // a file that doesn't exist yet is still watched, through its directory
+        let monitor = if path.is_dir() {
+            file.monitor_directory(flags, gio::Cancellable::NONE)
+        } else {
+            file.monitor_file(flags, gio::Cancellable::NONE)
+        };
+
+        let monitor = match monitor {
+            Ok(monitor) => monitor,
+            Err(e) => {
+                eprintln!("Can't watch {}: {e}", path.display());
+                return;
+            }
+        };
+
+        let watcher = Rc::downgrade(self);
+        monitor.connect_changed(move |_, _, _, event| {
+            if matches!(
+                event,
+                gio::FileMonitorEvent::PreUnmount | gio::FileMonitorEvent::Unmounted
+            ) {
+                return;
+            }
+
+            if let Some(watcher) = watcher.upgrade() {
+                watcher.queue(kind);
+            }
+        });
+
+        monitors.push(monitor);
+    }
+
+    fn queue(self: &Rc<Self>, kind: Changed) {
+        match kind {
+            Changed::Config => self.config_changed.set(true),
+            Changed::Styles => self.styles_changed.set(true),
+        }
+
+        if let Some(source) = self.pending.take() {
+            source.remove();
+        }
+
+        let watcher: Weak<Self> = Rc::downgrade(self);
+        let source = glib::timeout_add_local_once(DEBOUNCE, move || {
+            if let Some(watcher) = watcher.upgrade() {
+                watcher.pending.take();
+                watcher.flush();
+            }
+        });
+        self.pending.replace(Some(source));
+    }
+
+    fn flush(self: &Rc<Self>) {
+        if self.config_changed.take() {
+            (self.on_change)(Changed::Config);
+        }
+
+        if self.styles_changed.take() {
+            (self.on_change)(Changed::Styles);
+            self.rewatch();
+        }
+    }
+}